@@ -9,4 +9,5 @@
 mod papi_measurement;
 mod sample_formatter;
 
-pub use papi_measurement::PapiMeasurement;
+pub use papi_measurement::{PapiMeasurement, PapiValue};
+pub use sample_formatter::ThroughputMode;
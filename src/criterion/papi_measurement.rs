@@ -6,30 +6,101 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use super::sample_formatter::SampleFormatter;
-use crate::error::Result;
+use super::sample_formatter::{SampleFormatter, ThroughputMode};
+use crate::error::{ErrorKind, Result};
 use crate::event_set::{EventSetBuilder, ReadyEventSet, RunningEventSet, Sample};
 use crate::Papi;
 use criterion::measurement::{Measurement, ValueFormatter};
+use std::ops::Add;
+
+/// A hardware counter value that carries the name of its event
+///
+/// The values of additional events, that are measured together with the
+/// primary event, are recorded in `extra`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PapiValue {
+    pub event: &'static str,
+    pub value: i64,
+    pub extra: Vec<(&'static str, i64)>,
+}
+
+impl Add for PapiValue {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            event: self.event,
+            value: self.value + other.value,
+            extra: self
+                .extra
+                .into_iter()
+                .zip(other.extra.into_iter())
+                .map(|((event, value), (_, other))| (event, value + other))
+                .collect(),
+        }
+    }
+}
 
 /// An adapter for Criterion that measures hardware counters
+///
+/// Like Criterion's `WallTime`, each measurement covers a whole batch of
+/// iterations, i.e., `end` returns the total count of the batch and `add`
+/// sums up the totals of multiple batches. Criterion divides the totals by
+/// the number of iterations before its analysis, thus the reported values are
+/// per iteration. Normalizing the counts in the adapter would divide them
+/// twice.
+///
+/// Note that each batch includes the constant overhead of starting and
+/// stopping the event set. Criterion's linear regression of `iter` separates
+/// this overhead from the per-iteration count, but the mean of small batches
+/// in `iter_batched` includes it.
+///
+/// A measurement can count additional events together with its primary event
+/// (see `with_events`). Criterion's statistics and reports only cover the
+/// primary event, but the unit label in the report lists the additional
+/// events, and their values are recorded in `PapiValue::extra`.
 #[derive(Clone, Debug)]
 pub struct PapiMeasurement {
+    event_name: &'static str,
+    extra_event_names: Vec<&'static str>,
     ready_event_set: CloneableEventSet,
     sample: Sample,
     sample_formatter: SampleFormatter,
 }
 
 impl PapiMeasurement {
-    pub fn new(papi: &Papi, event_name: &'static str) -> Result<Self> {
+    /// Creates a measurement that counts a single event.
+    ///
+    /// The mode selects whether throughputs are reported as events per byte
+    /// or element, or as bytes or elements per event.
+    pub fn new(papi: &Papi, event_name: &'static str, mode: ThroughputMode) -> Result<Self> {
+        Self::with_events(papi, &[event_name], mode)
+    }
+
+    /// Creates a measurement that counts multiple events.
+    ///
+    /// The first event is the primary event, which Criterion analyzes. The
+    /// other events are measured in the same event set.
+    pub fn with_events(
+        papi: &Papi,
+        event_names: &[&'static str],
+        mode: ThroughputMode,
+    ) -> Result<Self> {
+        let (&event_name, extra_event_names) = event_names
+            .split_first()
+            .ok_or_else(|| ErrorKind::InvalidArgument("Cannot measure without events".into()))?;
+
         let ready_event_set = EventSetBuilder::new(papi)?
-            .add_event_by_name(event_name)?
+            .add_events_by_name(event_names)?
             .build()?;
         let mut sample = Sample::default();
         ready_event_set.init_sample(&mut sample)?;
-        let sample_formatter = SampleFormatter::new(event_name);
+        let sample_formatter =
+            SampleFormatter::with_extra_events(event_name, extra_event_names, mode);
 
         Ok(Self {
+            event_name,
+            extra_event_names: extra_event_names.to_vec(),
             ready_event_set: CloneableEventSet(ready_event_set),
             sample,
             sample_formatter,
@@ -39,7 +110,7 @@ impl PapiMeasurement {
 
 impl Measurement for PapiMeasurement {
     type Intermediate = RunningEventSet;
-    type Value = i64;
+    type Value = PapiValue;
 
     fn start(&self) -> Self::Intermediate {
         let ready_event_set = self.ready_event_set.clone().0;
@@ -53,23 +124,41 @@ impl Measurement for PapiMeasurement {
         running_event_set
             .stop(&mut sample)
             .expect("Failed to stop PAPI event set");
-        sample
-            .into_iter()
-            .nth(0)
-            .expect("Failed to get a value from PAPI sample; is the sample empty?")
-            .1
+        let value_of = |event| {
+            sample
+                .value_by_name(event)
+                .expect("Failed to get the event value from PAPI sample")
+        };
+
+        PapiValue {
+            event: self.event_name,
+            value: value_of(self.event_name),
+            extra: self
+                .extra_event_names
+                .iter()
+                .map(|&event| (event, value_of(event)))
+                .collect(),
+        }
     }
 
     fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
-        *v1 + *v2
+        v1.clone() + v2.clone()
     }
 
     fn zero(&self) -> Self::Value {
-        0
+        PapiValue {
+            event: self.event_name,
+            value: 0,
+            extra: self
+                .extra_event_names
+                .iter()
+                .map(|&event| (event, 0))
+                .collect(),
+        }
     }
 
     fn to_f64(&self, value: &Self::Value) -> f64 {
-        *value as f64
+        value.value as f64
     }
 
     fn formatter(&self) -> &dyn ValueFormatter {
@@ -85,3 +174,41 @@ impl Clone for CloneableEventSet {
         Self(self.0.try_clone().expect("Failed to clone PAPI event set"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn measure_two_events() {
+        let papi = Papi::init().unwrap();
+        let measurement = PapiMeasurement::with_events(
+            &papi,
+            &["PAPI_TOT_INS", "PAPI_TOT_CYC"],
+            ThroughputMode::ElementsPerEvent,
+        )
+        .unwrap();
+
+        let running = measurement.start();
+        let _sum: u64 = std::hint::black_box(0..1_000_000).sum();
+        let value = measurement.end(running);
+
+        assert_eq!(value.event, "PAPI_TOT_INS");
+        assert!(value.value > 0);
+        assert_eq!(value.extra.len(), 1);
+        assert_eq!(value.extra[0].0, "PAPI_TOT_CYC");
+        assert!(value.extra[0].1 > 0);
+
+        let total = measurement.add(&value, &measurement.zero());
+        assert_eq!(total, value);
+    }
+
+    #[test]
+    fn measure_without_events() {
+        let papi = Papi::init().unwrap();
+        assert!(
+            PapiMeasurement::with_events(&papi, &[], ThroughputMode::ElementsPerEvent).is_err()
+        );
+    }
+}
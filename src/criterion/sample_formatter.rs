@@ -9,16 +9,51 @@
 use criterion::measurement::ValueFormatter;
 use criterion::Throughput;
 
+/// The orientation of the throughput ratio in Criterion reports
+///
+/// For events that occur rarely, such as cache misses, events per byte or
+/// element are more intuitive than bytes or elements per event.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ThroughputMode {
+    EventsPerByte,
+    BytesPerEvent,
+    EventsPerElement,
+    ElementsPerEvent,
+}
+
 /// An adapter for Criterion that formats PAPI samples
 #[derive(Clone, Debug)]
 pub(crate) struct SampleFormatter {
     event_name: &'static str,
+    mode: ThroughputMode,
 }
 
 impl SampleFormatter {
     /// Creates a new SampleFormatter containing an event name
-    pub(crate) fn new(event_name: &'static str) -> Self {
-        Self { event_name }
+    pub(crate) fn new(event_name: &'static str, mode: ThroughputMode) -> Self {
+        Self { event_name, mode }
+    }
+
+    /// Creates a new SampleFormatter for a primary event that is measured
+    /// together with extra events
+    ///
+    /// The label lists the extra events, e.g., "PAPI_L1_DCM (+PAPI_TOT_CYC)".
+    pub(crate) fn with_extra_events(
+        event_name: &'static str,
+        extra: &[&'static str],
+        mode: ThroughputMode,
+    ) -> Self {
+        if extra.is_empty() {
+            return Self::new(event_name, mode);
+        }
+
+        // Criterion requires static labels. The label is created once per
+        // measurement, thus leaking it is bounded.
+        let label = format!("{} (+{})", event_name, extra.join(", +"));
+        Self {
+            event_name: Box::leak(label.into_boxed_str()),
+            mode,
+        }
     }
 }
 
@@ -34,6 +69,18 @@ impl SampleFormatter {
         values.iter_mut().for_each(|val| *val = elems / *val);
         "elems/event"
     }
+
+    /// Calculates throughput in events per byte
+    fn events_per_byte(&self, bytes: f64, values: &mut [f64]) -> &'static str {
+        values.iter_mut().for_each(|val| *val /= bytes);
+        "events/Byte"
+    }
+
+    /// Calculates throughput in events per element
+    fn events_per_element(&self, elems: f64, values: &mut [f64]) -> &'static str {
+        values.iter_mut().for_each(|val| *val /= elems);
+        "events/elem"
+    }
 }
 
 impl ValueFormatter for SampleFormatter {
@@ -47,9 +94,18 @@ impl ValueFormatter for SampleFormatter {
         throughput: &Throughput,
         values: &mut [f64],
     ) -> &'static str {
-        match *throughput {
-            Throughput::Bytes(bytes) => self.bytes_per_event(bytes as f64, values),
-            Throughput::Elements(elems) => self.elements_per_event(elems as f64, values),
+        // The mode selects the orientation of the ratio, and the throughput
+        // selects the unit
+        let events_per_unit = match self.mode {
+            ThroughputMode::EventsPerByte | ThroughputMode::EventsPerElement => true,
+            ThroughputMode::BytesPerEvent | ThroughputMode::ElementsPerEvent => false,
+        };
+
+        match (throughput, events_per_unit) {
+            (&Throughput::Bytes(bytes), true) => self.events_per_byte(bytes as f64, values),
+            (&Throughput::Bytes(bytes), false) => self.bytes_per_event(bytes as f64, values),
+            (&Throughput::Elements(elems), true) => self.events_per_element(elems as f64, values),
+            (&Throughput::Elements(elems), false) => self.elements_per_event(elems as f64, values),
         }
     }
 
@@ -57,3 +113,48 @@ impl ValueFormatter for SampleFormatter {
         self.event_name
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn scale(mode: ThroughputMode, throughput: Throughput) -> (Vec<f64>, &'static str) {
+        let formatter = SampleFormatter::new("PAPI_L1_DCM", mode);
+        let mut values = vec![2.0, 8.0];
+        let unit = formatter.scale_throughputs(0.0, &throughput, &mut values);
+        (values, unit)
+    }
+
+    #[test]
+    fn scale_events_per_byte() {
+        assert_eq!(
+            scale(ThroughputMode::EventsPerByte, Throughput::Bytes(4)),
+            (vec![0.5, 2.0], "events/Byte")
+        );
+    }
+
+    #[test]
+    fn scale_bytes_per_event() {
+        assert_eq!(
+            scale(ThroughputMode::BytesPerEvent, Throughput::Bytes(4)),
+            (vec![2.0, 0.5], "Bytes/event")
+        );
+    }
+
+    #[test]
+    fn scale_events_per_element() {
+        assert_eq!(
+            scale(ThroughputMode::EventsPerElement, Throughput::Elements(4)),
+            (vec![0.5, 2.0], "events/elem")
+        );
+    }
+
+    #[test]
+    fn scale_elements_per_event() {
+        assert_eq!(
+            scale(ThroughputMode::ElementsPerEvent, Throughput::Elements(4)),
+            (vec![2.0, 0.5], "elems/event")
+        );
+    }
+}
@@ -7,17 +7,84 @@
 // copied, modified, or distributed except according to those terms.
 
 use error_chain::error_chain;
-use std::ffi::CStr;
+use std::error::Error as StdError;
 use std::os::raw::c_int;
+use std::sync::{Arc, RwLock};
 
 use super::ffi;
+use super::Papi;
+
+/// Event conflicts with another event, or exists and cannot be added twice
+pub const ECNFLCT: c_int = ffi::PAPI_ECNFLCT;
+
+/// Event does not exist
+pub const ENOEVNT: c_int = ffi::PAPI_ENOEVNT;
+
+/// Insufficient memory
+pub const ENOMEM: c_int = ffi::PAPI_ENOMEM;
 
 // pub type Result<T> = result::Result<T, Error>;
 //
 pub fn check(code: c_int) -> Result<()> {
     match code as u32 {
         ffi::PAPI_OK => Ok(()),
-        _ => Err(ErrorKind::PapiError(code).into()),
+        _ => Err(ErrorKind::from_code(code).into()),
+    }
+}
+
+/// Creates the error of an event that failed to be added to an event set.
+///
+/// Codes with a specific error kind, e.g., `CounterConflict`, yield that kind
+/// chained with an `AddEventFailed` that names the event. All other codes
+/// yield an `AddEventFailed`.
+pub(crate) fn add_event_error(name: &str, code: c_int) -> Error {
+    let failed = Error::from(ErrorKind::AddEventFailed(name.to_string(), code));
+
+    match ErrorKind::from_code(code) {
+        ErrorKind::PapiError(_) => failed,
+        kind => Error::with_chain(failed, kind),
+    }
+}
+
+type CleanupErrorHook = Arc<dyn Fn(&Error) + Send + Sync + 'static>;
+
+static CLEANUP_ERROR_HOOK: RwLock<Option<CleanupErrorHook>> = RwLock::new(None);
+
+/// Sets a hook that handles errors occuring during the cleanup of PAPI
+/// resources.
+///
+/// Event sets release their PAPI resources when they are dropped. As `Drop`
+/// cannot return an error, a failed cleanup panics by default. Setting a hook
+/// routes the error to the hook instead, e.g., to log it. The hook replaces
+/// any previously set hook.
+///
+///     use papi::error::set_cleanup_error_hook;
+///
+///     set_cleanup_error_hook(|e| eprintln!("PAPI cleanup failed: {}", e));
+///
+pub fn set_cleanup_error_hook(hook: impl Fn(&Error) + Send + Sync + 'static) {
+    let mut guard = CLEANUP_ERROR_HOOK
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *guard = Some(Arc::new(hook));
+}
+
+/// Checks the return code of a cleanup function.
+///
+/// On failure, calls the cleanup error hook if one is set, and panics
+/// otherwise.
+pub(crate) fn check_cleanup(code: c_int, msg: &'static str) {
+    if let Err(e) = check(code) {
+        // Release the lock before calling the hook, such that the hook may
+        // set another hook, or panic without poisoning the lock
+        let hook = CLEANUP_ERROR_HOOK
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        match hook {
+            Some(hook) => hook(&Error::with_chain(e, msg)),
+            None => panic!("{}: {:?}", msg, e),
+        }
     }
 }
 
@@ -25,14 +92,7 @@ error_chain! {
     errors {
         PapiError(e: c_int) {
             description("PAPI command failed")
-            display("PAPI command returned with: '{}'",
-                        unsafe {
-                            let str_ptr = ffi::PAPI_strerror(*e);
-                            CStr::from_ptr(str_ptr)
-                                .to_str()
-                                .expect("Couldn't convert error message into UTF8 string")
-                        }
-                    )
+            display("PAPI command returned with: '{}'", Papi::strerror(*e))
         }
         InvalidEvent(e: &'static str) {
             description("invalid event name")
@@ -42,14 +102,150 @@ error_chain! {
             description("invalid argument")
             display("invalid argument: '{}'", e)
         }
+        EventNotFound(name: String) {
+            description("event not found")
+            display("event not found: '{}'", name)
+        }
+        AddEventFailed(name: String, code: c_int) {
+            description("failed to add event")
+            display("failed to add event '{}': {}", name, Papi::strerror(*code))
+        }
         OutOfHardwareCounters(e: &'static str) {
             description("out of hardware counters")
             display("out of hardware counters")
         }
+        CounterConflict {
+            description("event conflicts with another event")
+            display("event conflicts with another event")
+        }
+        OutOfMemory {
+            description("insufficient memory")
+            display("insufficient memory")
+        }
+        AlreadyRunning {
+            description("event set is already running")
+            display("event set is already running")
+        }
+        NotRunning {
+            description("event set is not running")
+            display("event set is not running")
+        }
+        PermissionDenied {
+            description("permission denied")
+            display("permission denied")
+        }
     }
 
     foreign_links {
         Io(::std::io::Error);
         TomlDe(toml::de::Error);
+        Json(serde_json::Error) #[cfg(feature = "json")];
+    }
+}
+
+impl ErrorKind {
+    /// Maps a PAPI return code to an error kind.
+    ///
+    /// Common codes map to specific error kinds. All other codes map to a
+    /// `PapiError`.
+    pub(crate) fn from_code(code: c_int) -> Self {
+        match code {
+            ffi::PAPI_ECNFLCT => ErrorKind::CounterConflict,
+            ffi::PAPI_ENOMEM => ErrorKind::OutOfMemory,
+            ffi::PAPI_EISRUN => ErrorKind::AlreadyRunning,
+            ffi::PAPI_ENOTRUN => ErrorKind::NotRunning,
+            ffi::PAPI_EPERM => ErrorKind::PermissionDenied,
+            _ => ErrorKind::PapiError(code),
+        }
+    }
+
+    /// Returns the PAPI return code of the error kind, if there is one.
+    fn code(&self) -> Option<c_int> {
+        match *self {
+            ErrorKind::PapiError(code) | ErrorKind::AddEventFailed(_, code) => Some(code),
+            ErrorKind::CounterConflict => Some(ffi::PAPI_ECNFLCT),
+            ErrorKind::OutOfMemory => Some(ffi::PAPI_ENOMEM),
+            ErrorKind::AlreadyRunning => Some(ffi::PAPI_EISRUN),
+            ErrorKind::NotRunning => Some(ffi::PAPI_ENOTRUN),
+            ErrorKind::PermissionDenied => Some(ffi::PAPI_EPERM),
+            _ => None,
+        }
+    }
+}
+
+impl Error {
+    /// Returns the PAPI return code that caused the error.
+    ///
+    /// Walks the chain of causes and returns the code of the first PAPI
+    /// error. Returns `None` if no PAPI call failed, e.g., for invalid
+    /// arguments.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     use papi::event_set::EventSetBuilder;
+    ///
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     let papi = papi::Papi::init()?;
+    ///     let builder = EventSetBuilder::new(&papi)?.add_event_by_name("PAPI_TOT_INS")?;
+    ///     if let Err(e) = builder.add_event_by_name("PAPI_TOT_INS") {
+    ///         if e.papi_code() == Some(papi::ECNFLCT) {
+    ///             println!("PAPI_TOT_INS is already in the event set");
+    ///         }
+    ///     }
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn papi_code(&self) -> Option<c_int> {
+        let mut cause: Option<&(dyn StdError + 'static)> = Some(self);
+
+        while let Some(e) = cause {
+            if let Some(code) = e.downcast_ref::<Error>().and_then(|e| e.kind().code()) {
+                return Some(code);
+            }
+            cause = e.source();
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn papi_code_of_chained_error() {
+        let error = Error::with_chain(Error::from(ErrorKind::PapiError(ENOMEM)), "cleanup");
+        assert_eq!(error.papi_code(), Some(ENOMEM));
+
+        let error = Error::from(ErrorKind::InvalidArgument("foo".into()));
+        assert_eq!(error.papi_code(), None);
+    }
+
+    #[test]
+    fn map_common_codes() {
+        let error = check(ffi::PAPI_ECNFLCT).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::CounterConflict));
+        assert_eq!(error.papi_code(), Some(ECNFLCT));
+
+        let error = check(ffi::PAPI_EISRUN).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::AlreadyRunning));
+
+        let error = check(ffi::PAPI_ENOEVNT).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::PapiError(ENOEVNT)));
+
+        assert!(check(ffi::PAPI_OK as c_int).is_ok());
+    }
+
+    #[test]
+    fn set_hook_from_cleanup_error_hook() {
+        set_cleanup_error_hook(|_| {
+            // Panic like without a hook, for the cleanups of other tests
+            set_cleanup_error_hook(|e| panic!("{:?}", e));
+        });
+
+        check_cleanup(ffi::PAPI_EINVAL, "Failed to cleanup PAPI event set");
     }
 }
@@ -14,7 +14,7 @@
 //!
 //! # Cloning
 //!
-//! The `EventSetBuilder` and `ReadyEventSet` can cloned with `try_clone`.
+//! The `EventSetBuilder` and `ReadyEventSet` can be cloned with `try_clone`.
 //! However, the user must ensure that the hardware has sufficient counter
 //! resources to handle multiple event set instances (i.e., `try_clone` or
 //! `start` can return an error).
@@ -68,32 +68,86 @@
 //!      #     println!("Summed up {}", collected);
 //!      # }
 
-use super::error::{check, ErrorKind, Result};
+use super::error::{add_event_error, check, check_cleanup, ErrorKind, Result, ResultExt};
+use super::events::{component_index, event_name_to_code, EventCode};
 use super::ffi;
-use super::Papi;
+use super::hardware::hw_info;
+use super::overflow;
+use super::{LibraryRef, Papi, ThreadGuard};
+use std::any::Any;
+use std::cell::{Cell, RefCell};
 use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::convert::TryInto;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
+use std::mem;
 use std::num::NonZeroU16;
 use std::os::raw::c_char;
+use std::panic::{self, UnwindSafe};
 use std::ptr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The length of the binary sample header, i.e., the event set hash, the
+/// number of events, and the multiplexing flag.
+const SAMPLE_HEADER_LEN: usize = mem::size_of::<u64>() + mem::size_of::<u32>() + 1;
+
+/// The length of an event code and its value in a binary sample.
+const SAMPLE_EVENT_LEN: usize = mem::size_of::<i32>() + mem::size_of::<i64>();
+
+/// The events that run on the fixed-function counters of Intel CPUs.
+const FIXED_COUNTER_EVENTS: [&str; 6] = [
+    "PAPI_TOT_INS",
+    "PAPI_TOT_CYC",
+    "PAPI_REF_CYC",
+    "INSTRUCTION_RETIRED",
+    "UNHALTED_CORE_CYCLES",
+    "UNHALTED_REFERENCE_CYCLES",
+];
 
 /// An event set that is ready to sample hardware events.
+///
+/// A `ReadyEventSet` is `Send`, e.g., to build it on one thread and measure
+/// on another. However, PAPI binds the event set to the thread that added its
+/// events, and moving the event set does not change this binding. The caller
+/// must call `rebind_to_current_thread` on the new thread before starting the
+/// event set there. Without rebinding, the event set is started on the new
+/// thread while it is still bound to the previous thread. PAPI then either
+/// fails to start it, or counts the previous thread.
+///
+/// A `ReadyEventSet` is not `Sync`, because PAPI does not support using an
+/// event set from several threads at once. The `PhantomData<Cell<()>>` marker
+/// keeps it from implementing `Sync`.
 #[derive(Debug)]
 pub struct ReadyEventSet {
+    name: String,
     event_set: Option<i32>,
     event_set_hash: u64,
     num_events: NonZeroU16,
-    phantom: PhantomData<*mut u8>, // unimplement Send and Sync
+    options: EventSetOptions,
+    overflow_thread: Option<thread::ThreadId>, // registered overflow handlers
+    timer_source: TimerSource,
+    library: LibraryRef,
+    phantom: PhantomData<Cell<()>>, // unimplement Sync
 }
 
 /// An already running event set.
 #[derive(Debug)]
 pub struct RunningEventSet {
+    name: String,
     event_set: Option<i32>,
     event_set_hash: u64,
     num_events: NonZeroU16,
+    options: EventSetOptions,
+    overflow_thread: Option<thread::ThreadId>, // registered overflow handlers
+    timer_source: TimerSource,
+    library: LibraryRef,
+    start_nsec: i64,
+    paused: Cell<bool>,
+    paused_values: RefCell<Vec<i64>>,
+    last_read_values: RefCell<Vec<i64>>,
     phantom: PhantomData<*mut u8>, // unimplement Send and Sync
 }
 
@@ -102,11 +156,72 @@ pub struct RunningEventSet {
 #[derive(Debug)]
 pub struct EventSetBuilder<'p> {
     papi: &'p Papi,
+    name: String,
     event_set: Option<i32>,
     num_events: u16,
+    options: EventSetOptions,
+    overcommit: bool,
+    timer_source: TimerSource,
+    event_codes: Vec<i32>, // scratch buffer for listing the events
+    fixed_counter_events: Option<Vec<i32>>, // resolved on first use
     phantom: PhantomData<*mut u8>, // unimplement Send and Sync
 }
 
+/// The timer that measures the duration of timed measurements.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TimerSource {
+    /// Wall clock time
+    #[default]
+    Real,
+    /// Time that the process spent running on a CPU
+    Virtual,
+}
+
+/// The scope of the events that an event set counts.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Granularity {
+    /// Count events of the current thread
+    Thread,
+    /// Count events of the current process
+    Process,
+    /// Count events of the current process group
+    ProcessGroup,
+    /// Count events of all processes on the current CPU
+    System,
+    /// Count events of all processes on all CPUs
+    SystemCpu,
+}
+
+/// The execution modes in which an event set counts events.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Domain {
+    /// Count events in user mode
+    User,
+    /// Count events in kernel mode
+    Kernel,
+    /// Count events in other modes, e.g., in the hypervisor
+    Other,
+    /// Count events in supervisor mode
+    Supervisor,
+    /// Count events in all modes
+    All,
+}
+
+/// The options of an event set.
+///
+/// PAPI doesn't copy options between event sets. Thus, the options are
+/// recorded to apply them to clones of an event set.
+#[derive(Clone, Copy, Debug, Default)]
+struct EventSetOptions {
+    component: Option<i32>,
+    attached_pid: Option<libc::pid_t>,
+    attached_cpu: Option<u32>,
+    inherit: bool,
+    granularity: Option<Granularity>,
+    domain: Option<Domain>,
+    multiplexed: bool,
+}
+
 /// A collection of sampled hardware event values.
 ///
 ///     # use std::error::Error;
@@ -137,6 +252,42 @@ pub struct Sample {
     event_set_hash: u64,
     event_codes: Vec<i32>,
     values: Vec<i64>,
+    multiplexed: bool,
+    name: String,
+    library: Option<LibraryRef>, // keeps PAPI initialized to resolve event names
+}
+
+/// The panic payload of a workload that panicked during a measurement.
+///
+/// Carries the sample measured until the panic occurred, together with the
+/// original panic payload.
+#[derive(Debug)]
+pub struct MeasurementPanic {
+    pub sample: Sample,
+    pub payload: Box<dyn Any + Send + 'static>,
+}
+
+/// A reader that returns the change of the counters since the previous read.
+///
+/// Packages the common polling pattern of reading a running event set
+/// periodically. The reader owns its samples, and thus does not allocate
+/// after its construction.
+#[derive(Debug)]
+pub struct DeltaReader {
+    running: RunningEventSet,
+    previous: Sample,
+    current: Sample,
+    delta: Sample,
+}
+
+/// An iterator that reads the change of the counters at a fixed period.
+///
+/// Created by `RunningEventSet::sample_stream`.
+#[derive(Debug)]
+pub struct SampleStream {
+    running: RunningEventSet,
+    period: Duration,
+    deadline: Instant,
 }
 
 impl ReadyEventSet {
@@ -161,14 +312,26 @@ impl ReadyEventSet {
     ///     # }
     ///
     pub fn start(mut self) -> Result<RunningEventSet> {
-        unsafe {
-            check(ffi::PAPI_start(self.event_set.unwrap()))?;
-        }
+        let start_nsec = self.timer_source.now_nsec();
+        with_name(
+            unsafe { check(ffi::PAPI_start(self.event_set.unwrap())) },
+            &self.name,
+            "start",
+        )?;
 
         Ok(RunningEventSet {
+            name: std::mem::take(&mut self.name),
             event_set: self.event_set.take(),
             event_set_hash: self.event_set_hash,
             num_events: self.num_events,
+            options: self.options,
+            overflow_thread: self.overflow_thread,
+            timer_source: self.timer_source,
+            library: self.library.clone(),
+            start_nsec,
+            paused: Cell::new(false),
+            paused_values: RefCell::new(vec![0; self.num_events.get() as usize]),
+            last_read_values: RefCell::new(vec![0; self.num_events.get() as usize]),
             phantom: PhantomData,
         })
     }
@@ -200,38 +363,31 @@ impl ReadyEventSet {
     ///     # }
     ///
     pub fn init_sample(&self, sample: &mut Sample) -> Result<()> {
-        let num_events = self.num_events.get().into();
-        let mut num_events_ffi = self.num_events.get().into();
         let event_set = self
             .event_set
             .expect("EventSet uninitialized; looks like a bug");
 
-        sample.event_set_hash = self.event_set_hash;
-
-        sample.event_codes.clear();
-        sample.event_codes.resize(num_events, 0);
-
-        sample.values.clear();
-        sample.values.resize(num_events, 0);
-
-        unsafe {
-            check(ffi::PAPI_list_events(
-                event_set,
-                sample.event_codes.as_mut_ptr(),
-                &mut num_events_ffi,
-            ))?;
-        }
-
-        Ok(())
+        init_sample(
+            event_set,
+            self.event_set_hash,
+            self.num_events,
+            &self.name,
+            &self.library,
+            sample,
+        )
     }
 
-    /// Creates a new, distinct `ReadyEventSet` instance containing the same
-    /// events as the given `ReadyEventSet` instance.
+    /// Sets the values that the hardware counters start from.
+    ///
+    /// The values are in the same order as the events of the event set.
+    /// Returns an error if the number of values doesn't match the number of
+    /// events. Note that not all components support writing counters, e.g.,
+    /// the `perf_event` component returns an error.
     ///
     ///     # use std::error::Error;
     ///     # use std::result::Result;
     ///     # use papi::Papi;
-    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     # use papi::event_set::EventSetBuilder;
     ///     #
     ///     # fn main() -> Result<(), Box<dyn Error>> {
     ///     # let papi = Papi::init()?;
@@ -239,107 +395,126 @@ impl ReadyEventSet {
     ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
     ///     #     .build()?;
     ///     #
-    ///     let cloned_event_set = ready_event_set.try_clone()?;
+    ///     ready_event_set.set_start_values(&[1000])?;
+    ///     #
     ///     # Ok(())
     ///     # }
     ///
-    pub fn try_clone(&self) -> Result<Self> {
-        let num_events = self.num_events.get().into();
-        let mut num_events_ffi = self.num_events.get().into();
+    pub fn set_start_values(&self, values: &[i64]) -> Result<()> {
         let event_set = self
             .event_set
             .expect("EventSet uninitialized; looks like a bug");
-        let mut new_event_set = ffi::PAPI_NULL;
-        let mut event_codes = vec![0; num_events];
 
-        unsafe {
-            check(ffi::PAPI_list_events(
-                event_set,
-                event_codes.as_mut_ptr(),
-                &mut num_events_ffi,
-            ))?;
-            check(ffi::PAPI_create_eventset(&mut new_event_set))?;
-            check(ffi::PAPI_add_events(
-                new_event_set,
-                event_codes.as_mut_ptr(),
-                num_events_ffi,
-            ))?;
+        if values.len() != self.num_events.get() as usize {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Expected {} start values, got {}",
+                self.num_events,
+                values.len()
+            )))?;
         }
 
-        Ok(ReadyEventSet {
-            event_set: Some(new_event_set),
-            event_set_hash: self.event_set_hash,
-            num_events: self.num_events,
-            phantom: PhantomData,
-        })
-    }
-}
-
-impl Drop for ReadyEventSet {
-    fn drop(&mut self) {
-        if let Some(ref mut es) = self.event_set.take() {
-            unsafe {
-                check(ffi::PAPI_cleanup_eventset(*es)).expect("Failed to cleanup PAPI event set");
-                check(ffi::PAPI_destroy_eventset(es)).expect("Failed to destroy PAPI event set");
-            }
-        }
+        let mut values = values.to_vec();
+        with_name(
+            unsafe { check(ffi::PAPI_write(event_set, values.as_mut_ptr())) },
+            &self.name,
+            "write start values",
+        )
     }
-}
 
-impl RunningEventSet {
-    /// Accumulates the hardware events sepecified by the event set onto the
-    /// given `Sample`.
+    /// Binds the event set to the calling thread.
     ///
-    /// The hardware counters are reset and continue running after the
-    /// accumulation.
+    /// Registers the calling thread with PAPI, and re-adds the events of the
+    /// event set, such that they count the calling thread. Call this after
+    /// moving the event set to another thread, and before starting it on that
+    /// thread. Samples of the event set remain valid.
+    ///
+    /// Returns the rebound event set and a guard of the thread registration.
+    /// Keep the guard alive while using the event set on the calling thread.
+    /// The thread is unregistered from PAPI when the guard is dropped.
+    ///
+    /// The options of the event set, e.g., multiplexing or the domain, are
+    /// re-applied before re-adding the events. If rebinding fails, the event
+    /// set is destroyed, as its events may already be removed.
+    ///
+    /// Re-adding the events removes overflow handlers from the event set.
+    /// Register overflow handlers only after rebinding the event set. An
+    /// event set with overflow handlers can only be rebound on the thread
+    /// that registered the handlers, because the handlers cannot be removed
+    /// from another thread.
     ///
     ///     # use std::error::Error;
     ///     # use std::result::Result;
     ///     # use papi::Papi;
-    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     # use papi::event_set::EventSetBuilder;
     ///     #
     ///     # fn main() -> Result<(), Box<dyn Error>> {
     ///     # let papi = Papi::init()?;
-    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
-    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
-    ///     #     .build()?;
-    ///     #
-    ///     let mut sample = Sample::default();
-    ///     ready_event_set.init_sample(&mut sample)?;
-    ///     let running_event_set = ready_event_set.start()?;
+    ///     let ready_event_set = EventSetBuilder::new(&papi)?
+    ///         .add_event_by_name("PAPI_TOT_INS")?
+    ///         .build()?;
     ///
-    ///     running_event_set.accum(&mut sample)?;
+    ///     let handle = std::thread::spawn(move || -> papi::error::Result<()> {
+    ///         let (ready_event_set, _guard) = ready_event_set.rebind_to_current_thread()?;
+    ///         let (_, sample) = ready_event_set.measure(|| (0..100).sum::<u32>())?;
+    ///         Ok(())
+    ///     });
+    ///     handle.join().unwrap()?;
     ///     #
-    ///     # running_event_set.stop(&mut sample)?;
     ///     # Ok(())
     ///     # }
     ///
-    pub fn accum(&self, sample: &mut Sample) -> Result<()> {
+    pub fn rebind_to_current_thread(mut self) -> Result<(Self, ThreadGuard)> {
         let event_set = self
             .event_set
             .expect("EventSet uninitialized; looks like a bug");
+        let mut event_codes = list_event_codes(event_set, self.num_events.get())?;
 
-        if sample.event_set_hash != self.event_set_hash {
-            Err(ErrorKind::InvalidArgument(
-                "Sample is not initialized".into(),
-            ))?;
+        if let Some(owner) = self.overflow_thread {
+            if owner != thread::current().id() {
+                Err(ErrorKind::InvalidArgument(format!(
+                    "Cannot rebind event set '{}', as its overflow handlers are registered on another thread",
+                    self.name
+                )))?;
+            }
         }
 
-        unsafe {
-            check(ffi::PAPI_accum(event_set, sample.values.as_mut_ptr()))?;
-        }
+        let guard = with_name(
+            ThreadGuard::register(self.library.clone()),
+            &self.name,
+            "rebind to the current thread",
+        )?;
 
-        Ok(())
+        overflow::unregister(event_set, self.overflow_thread.take());
+        let rebound = unsafe {
+            check(ffi::PAPI_cleanup_eventset(event_set))
+                .and_then(|_| self.options.apply(event_set))
+                .and_then(|_| {
+                    check(ffi::PAPI_add_events(
+                        event_set,
+                        event_codes.as_mut_ptr(),
+                        event_codes.len() as i32,
+                    ))
+                })
+        };
+
+        match with_name(rebound, &self.name, "rebind to the current thread") {
+            Ok(()) => Ok((self, guard)),
+            Err(e) => {
+                // Destroy the event set before unregistering the thread
+                drop(self);
+                Err(e)
+            }
+        }
     }
 
-    /// Reads the hardware events sepecified by the event set.
+    /// Lists the names of the events in the event set.
     ///
-    /// The hardware counters continue running after the read.
+    /// The names are in the same order as the values of a `Sample`.
     ///
     ///     # use std::error::Error;
     ///     # use std::result::Result;
     ///     # use papi::Papi;
-    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     # use papi::event_set::EventSetBuilder;
     ///     #
     ///     # fn main() -> Result<(), Box<dyn Error>> {
     ///     # let papi = Papi::init()?;
@@ -347,37 +522,26 @@ impl RunningEventSet {
     ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
     ///     #     .build()?;
     ///     #
-    ///     let mut sample = Sample::default();
-    ///     ready_event_set.init_sample(&mut sample)?;
-    ///     let running_event_set = ready_event_set.start()?;
-    ///
-    ///     running_event_set.read(&mut sample)?;
+    ///     for name in ready_event_set.event_names()? {
+    ///         println!("{}", name);
+    ///     }
     ///     #
-    ///     # running_event_set.stop(&mut sample)?;
     ///     # Ok(())
     ///     # }
     ///
-    pub fn read(&self, sample: &mut Sample) -> Result<()> {
+    pub fn event_names(&self) -> Result<Vec<String>> {
         let event_set = self
             .event_set
             .expect("EventSet uninitialized; looks like a bug");
 
-        if sample.event_set_hash != self.event_set_hash {
-            Err(ErrorKind::InvalidArgument(
-                "Sample is not initialized".into(),
-            ))?;
-        }
-
-        unsafe {
-            check(ffi::PAPI_read(event_set, sample.values.as_mut_ptr()))?;
-        }
-
-        Ok(())
+        list_event_codes(event_set, self.num_events.get())?
+            .into_iter()
+            .map(Sample::event_code_to_name)
+            .collect()
     }
 
-    /// Stops sampling the hardware events specified by the event set.
-    ///
-    /// Note that this method destroys the event set.
+    /// Creates a new, distinct `ReadyEventSet` instance containing the same
+    /// events as the given `ReadyEventSet` instance.
     ///
     ///     # use std::error::Error;
     ///     # use std::result::Result;
@@ -390,56 +554,94 @@ impl RunningEventSet {
     ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
     ///     #     .build()?;
     ///     #
-    ///     let mut sample = Sample::default();
-    ///     ready_event_set.init_sample(&mut sample)?;
-    ///     let running_event_set = ready_event_set.start()?;
+    ///     let cloned_event_set = ready_event_set.try_clone()?;
+    ///     # Ok(())
+    ///     # }
     ///
-    ///     running_event_set.stop(&mut sample)?;
+    pub fn try_clone(&self) -> Result<Self> {
+        let mut event_codes = self.list_events()?;
+        self.clone_with_events(&mut event_codes)
+    }
+
+    /// Creates `n` new, distinct `ReadyEventSet` instances containing the same
+    /// events as the given `ReadyEventSet` instance.
+    ///
+    /// In contrast to calling `try_clone` `n` times, the events are listed
+    /// only once. If creating an instance fails, e.g., due to insufficient
+    /// hardware counters, the instances created so far are destroyed and the
+    /// error is returned.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
     ///     #
+    ///     let cloned_event_sets = ready_event_set.try_clone_n(4)?;
     ///     # Ok(())
     ///     # }
     ///
-    pub fn stop(self, sample: &mut Sample) -> Result<()> {
+    pub fn try_clone_n(&self, n: usize) -> Result<Vec<Self>> {
+        let mut event_codes = self.list_events()?;
+        (0..n)
+            .map(|_| self.clone_with_events(&mut event_codes))
+            .collect()
+    }
+
+    /// Lists the event codes of the event set.
+    fn list_events(&self) -> Result<Vec<i32>> {
         let event_set = self
             .event_set
             .expect("EventSet uninitialized; looks like a bug");
 
-        if sample.event_set_hash != self.event_set_hash {
-            Err(ErrorKind::InvalidArgument(
-                "Sample is not initialized".into(),
-            ))?;
-        }
+        list_event_codes(event_set, self.num_events.get())
+    }
+
+    /// Creates a new event set containing the given events, with the same
+    /// options as the event set.
+    ///
+    /// The new event set is destroyed on failure.
+    fn clone_with_events(&self, event_codes: &mut [i32]) -> Result<Self> {
+        let mut new_event_set = ffi::PAPI_NULL;
 
         unsafe {
-            check(ffi::PAPI_stop(event_set, sample.values.as_mut_ptr()))?;
+            check(ffi::PAPI_create_eventset(&mut new_event_set))?;
         }
 
-        Ok(())
-    }
-}
-
-impl Drop for RunningEventSet {
-    fn drop(&mut self) {
-        if let Some(ref mut es) = self.event_set.take() {
-            unsafe {
-                let mut state = 0;
-                check(ffi::PAPI_state(*es, &mut state)).expect("Failed to get PAPI counter state");
-                if (state as u32 & ffi::PAPI_RUNNING) != 0 {
-                    check(ffi::PAPI_stop(*es, ptr::null_mut()))
-                        .expect("Failed to stop PAPI counters");
-                }
+        let cloned = ReadyEventSet {
+            name: self.name.clone(),
+            event_set: Some(new_event_set),
+            event_set_hash: self.event_set_hash,
+            num_events: self.num_events,
+            options: self.options,
+            overflow_thread: None,
+            timer_source: self.timer_source,
+            library: self.library.clone(),
+            phantom: PhantomData,
+        };
 
-                check(ffi::PAPI_cleanup_eventset(*es)).expect("Failed to cleanup PAPI event set");
-                check(ffi::PAPI_destroy_eventset(es)).expect("Failed to destroy PAPI event set");
-            }
+        self.options.apply(new_event_set)?;
+        unsafe {
+            check(ffi::PAPI_add_events(
+                new_event_set,
+                event_codes.as_mut_ptr(),
+                event_codes.len() as i32,
+            ))?;
         }
+
+        Ok(cloned)
     }
-}
 
-impl<'p> EventSetBuilder<'p> {
-    /// Creates a new EventSetBuilder.
+    /// Measures a workload `n` times and returns a `Sample` for each
+    /// iteration.
     ///
-    /// This requires an initialized PAPI instance.
+    /// The event set is started once. The counters are reset before each
+    /// iteration and read after it, but keep running in between iterations.
     ///
     ///     # use std::error::Error;
     ///     # use std::result::Result;
@@ -448,27 +650,47 @@ impl<'p> EventSetBuilder<'p> {
     ///     #
     ///     # fn main() -> Result<(), Box<dyn Error>> {
     ///     # let papi = Papi::init()?;
-    ///     let builder = EventSetBuilder::new(&papi)?;
-    ///     # assert!(builder.add_event_by_name("CPU_CLK_UNHALTED").is_ok());
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     #
+    ///     let samples = ready_event_set.measure_each(10, || {
+    ///         let _sum: u32 = (0..100).sum();
+    ///     })?;
+    ///     assert_eq!(samples.len(), 10);
+    ///     #
     ///     # Ok(())
     ///     # }
     ///
-    pub fn new(papi: &'p Papi) -> Result<Self> {
-        let mut event_set = ffi::PAPI_NULL;
+    pub fn measure_each<F: FnMut()>(self, n: usize, mut f: F) -> Result<Vec<Sample>> {
+        let mut template = Sample::default();
+        self.init_sample(&mut template)?;
+        let mut samples = vec![template.clone(); n];
 
-        unsafe {
-            check(ffi::PAPI_create_eventset(&mut event_set))?;
+        let running = self.start()?;
+
+        for sample in samples.iter_mut() {
+            running.reset()?;
+            f();
+            running.read(sample)?;
         }
 
-        Ok(Self {
-            papi,
-            event_set: Some(event_set),
-            num_events: 0,
-            phantom: PhantomData,
-        })
+        running.stop(&mut template)?;
+
+        Ok(samples)
     }
 
-    /// Finalizes the building of a new `ReadyEventSet`.
+    /// Measures a workload `n` times and returns its overhead-corrected
+    /// counts per iteration.
+    ///
+    /// First measures `n` iterations of an empty workload, then `n`
+    /// iterations of the real workload. Both are called through the same
+    /// loop, such that the loop and call overhead is subtracted from the
+    /// counts. The difference is divided by `n`.
+    ///
+    /// As measurements are noisy, the correction can yield small negative
+    /// values for workloads that are cheap in some events. Use
+    /// `Sample::clamp_negative` to clamp these values to zero.
     ///
     ///     # use std::error::Error;
     ///     # use std::result::Result;
@@ -477,46 +699,57 @@ impl<'p> EventSetBuilder<'p> {
     ///     #
     ///     # fn main() -> Result<(), Box<dyn Error>> {
     ///     # let papi = Papi::init()?;
-    ///     # let builder = EventSetBuilder::new(&papi)?
-    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
     ///     #
-    ///     let ready_event_set = builder.build()?;
+    ///     let mut sample = ready_event_set.measure_corrected(100, || {
+    ///         let _sum: u32 = (0..100).sum();
+    ///     })?;
+    ///     sample.clamp_negative();
     ///     #
     ///     # Ok(())
     ///     # }
     ///
-    pub fn build(mut self) -> Result<ReadyEventSet> {
-        let num_events = NonZeroU16::new(self.num_events).ok_or_else(|| {
-            ErrorKind::InvalidArgument("Cannot create EventSet without events!".into())
-        })?;
-        let mut num_events_ffi = self.num_events.into();
-        let mut event_codes = vec![0; self.num_events.into()];
-
-        let event_set = self
-            .event_set
-            .expect("EventSet uninitialized; looks like a bug");
-
-        unsafe {
-            check(ffi::PAPI_list_events(
-                event_set,
-                event_codes.as_mut_ptr(),
-                &mut num_events_ffi,
+    pub fn measure_corrected<F: FnMut()>(self, n: usize, mut f: F) -> Result<Sample> {
+        if n == 0 {
+            Err(ErrorKind::InvalidArgument(
+                "Cannot measure zero iterations".into(),
             ))?;
         }
 
-        let mut hasher = DefaultHasher::new();
-        event_codes.iter().for_each(|code| code.hash(&mut hasher));
-        let event_set_hash = hasher.finish();
+        let mut baseline = Sample::default();
+        self.init_sample(&mut baseline)?;
+        let mut sample = baseline.clone();
 
-        Ok(ReadyEventSet {
-            event_set: self.event_set.take(),
-            event_set_hash,
-            num_events,
-            phantom: PhantomData,
-        })
+        let running = self.start()?;
+
+        running.reset()?;
+        run_iterations(n, &mut || {});
+        running.read(&mut baseline)?;
+
+        running.reset()?;
+        run_iterations(n, &mut f);
+        running.stop(&mut sample)?;
+
+        sample
+            .values
+            .iter_mut()
+            .zip(baseline.values.iter())
+            .for_each(|(value, base)| *value = (*value - base) / n as i64);
+
+        Ok(sample)
     }
 
-    /// Adds a hardware event specified by its name to the event set.
+    /// Measures a workload and returns its result together with the sample.
+    ///
+    /// If the workload panics, the event set is stopped before the panic is
+    /// resumed. The panic payload is replaced by a `MeasurementPanic`, which
+    /// contains the sample measured up to the panic and the original payload.
+    ///
+    /// The workload must be `UnwindSafe`, as it is run inside
+    /// `std::panic::catch_unwind`. Wrap the workload in
+    /// `std::panic::AssertUnwindSafe` if it captures mutable references.
     ///
     ///     # use std::error::Error;
     ///     # use std::result::Result;
@@ -525,230 +758,3909 @@ impl<'p> EventSetBuilder<'p> {
     ///     #
     ///     # fn main() -> Result<(), Box<dyn Error>> {
     ///     # let papi = Papi::init()?;
-    ///     # let builder = EventSetBuilder::new(&papi)?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
     ///     #
-    ///     builder.add_event_by_name("CPU_CLK_UNHALTED")?;
+    ///     let (sum, sample) = ready_event_set.measure(|| (0..100).sum::<u32>())?;
     ///     #
     ///     # Ok(())
     ///     # }
     ///
-    pub fn add_event_by_name(mut self, name: &str) -> Result<Self> {
-        // Check if there are enough hardware counters available before adding
-        // another event counter
-        let num_events = unsafe { ffi::PAPI_num_events(self.event_set.unwrap()) };
-        if num_events < 0 {
-            check(num_events)?;
-        }
-        let num_counters = unsafe { ffi::PAPI_num_counters() };
-        if num_counters < 0 {
-            check(num_counters)?;
-        } else if num_events == num_counters {
-            Err(ErrorKind::OutOfHardwareCounters(
-                "Too many hardware events specified",
-            ))?;
-        }
+    pub fn measure<T, F>(self, f: F) -> Result<(T, Sample)>
+    where
+        F: FnOnce() -> T + UnwindSafe,
+    {
+        let mut sample = Sample::default();
+        self.init_sample(&mut sample)?;
 
-        let c_name = std::ffi::CString::new(name)
-            .or_else(|_| Err(ErrorKind::InvalidEvent("Invalid event name")))?;
+        let running = self.start()?;
+        let result = panic::catch_unwind(f);
+        let stopped = running.stop(&mut sample);
 
-        // Get event code
-        let mut code: i32 = 0;
-        unsafe {
-            check(ffi::PAPI_event_name_to_code(c_name.as_ptr(), &mut code))?;
-            check(ffi::PAPI_add_event(self.event_set.unwrap(), code))?;
+        match result {
+            Ok(value) => {
+                stopped?;
+                Ok((value, sample))
+            }
+            Err(payload) => panic::resume_unwind(Box::new(MeasurementPanic { sample, payload })),
         }
-
-        self.num_events += 1;
-
-        Ok(self)
     }
 
-    /// Adds the events from a preset to the event set.
+    /// Calls a handler each time an event counts past a threshold.
+    ///
+    /// Enables statistical sampling, similar to `perf record`. Each time the
+    /// counter of the event increases by `threshold`, the handler is called
+    /// with the event code and the program counter at the time of the
+    /// overflow. The event must be part of the event set. PAPI supports
+    /// overflows only on some events; derived events, for instance, cannot
+    /// overflow.
     ///
-    /// Presets are specified by the configuration file.
+    /// The handler runs inside a signal handler. Thus, it must be
+    /// async-signal-safe, e.g., it must not allocate memory or take locks.
     ///
     ///     # use std::error::Error;
     ///     # use std::result::Result;
     ///     # use papi::Papi;
-    ///     use papi::Config;
-    ///     # use papi::event_set::EventSetBuilder;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     use std::cell::Cell;
+    ///     use std::rc::Rc;
     ///
     ///     # fn main() -> Result<(), Box<dyn Error>> {
-    ///     let config_str = r#"
-    ///     [presets]
-    ///     Test1 = ["UOPS_RETIRED:ALL", "UOPS_RETIRED:STALL_CYCLES"]
-    ///     Test2 = ["UOPS_EXECUTED:CORE", "UOPS_EXECUTED:STALL_CYCLES"]
-    ///     Test3 = ["UOPS_EXECUTED:THREAD"]
-    ///     "#;
+    ///     # let papi = Papi::init()?;
+    ///     let overflows = Rc::new(Cell::new(0));
+    ///     let counter = overflows.clone();
     ///
-    ///     let config = Config::parse_str(&config_str)?;
-    ///     # let papi = Papi::init_with_config(config)?;
-    ///     # let builder = EventSetBuilder::new(&papi)?;
-    ///     builder.use_preset("Test1")?;
+    ///     let ready_event_set = EventSetBuilder::new(&papi)?
+    ///         .add_event_by_name("PAPI_TOT_CYC")?
+    ///         .build()?
+    ///         .overflow("PAPI_TOT_CYC", 1_000_000, move |_code, _pc| {
+    ///             counter.set(counter.get() + 1);
+    ///         })?;
+    ///
+    ///     let (_sum, _sample) = ready_event_set.measure(|| (0..10_000_000_u64).sum::<u64>())?;
+    ///     println!("Overflows: {}", overflows.get());
     ///     #
     ///     # Ok(())
     ///     # }
     ///
-    pub fn use_preset(mut self, name: &str) -> Result<Self> {
-        let maybe_config = match &self.papi.config {
-            Some(o) => &o.presets,
-            None => Err(ErrorKind::InvalidArgument("No configuration set".into()))?,
-        };
-
-        let maybe_val = match maybe_config {
-            Some(c) => c.get(name),
-            None => Err(ErrorKind::InvalidArgument("No presets configured".into()))?,
-        };
+    pub fn overflow<F>(mut self, event_name: &str, threshold: i32, handler: F) -> Result<Self>
+    where
+        F: FnMut(i32, usize) + 'static,
+    {
+        let event_set = self
+            .event_set
+            .expect("EventSet uninitialized; looks like a bug");
+        let event_code = event_name_to_code(event_name)?;
+        let event_codes = self.list_events()?;
 
-        let preset = match maybe_val {
-            Some(v) => v,
-            None => Err(ErrorKind::InvalidArgument(format!(
-                "Preset {} doesn't exist",
-                name
-            )))?,
-        };
+        if !event_codes.contains(&event_code) {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Event {} is not in the event set",
+                event_name
+            )))?;
+        }
 
-        for p in preset {
-            self = self.add_event_by_name(&p)?;
+        let current_thread = thread::current().id();
+        if let Some(owner) = self.overflow_thread {
+            if owner != current_thread {
+                Err(ErrorKind::InvalidArgument(
+                    "Overflow handlers of the event set are registered on another thread".into(),
+                ))?;
+            }
         }
 
+        overflow::register(
+            event_set,
+            event_codes,
+            event_code,
+            event_name,
+            threshold,
+            Box::new(handler),
+            self.overflow_thread.is_none(),
+        )?;
+        self.overflow_thread = Some(current_thread);
+
         Ok(self)
     }
 
-    /// Creates a new, distinct `EventSetBuilder` instance containing the same
-    /// events as the given `EventSetBuilder` instance.
+    /// Destroys the event set and releases its PAPI resources.
     ///
-    /// WARNING: Not yet implemented.
-    pub fn try_clone(&self) -> Result<Self> {
-        unimplemented!();
+    /// In contrast to dropping the event set, this returns cleanup errors
+    /// instead of panicking or calling the cleanup error hook. The event set
+    /// is destroyed even if cleaning it up fails.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     #
+    ///     ready_event_set.destroy()?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn destroy(mut self) -> Result<()> {
+        match self.event_set.take() {
+            Some(es) => destroy_event_set(es, self.overflow_thread),
+            None => Ok(()),
+        }
     }
 }
 
-impl Drop for EventSetBuilder<'_> {
+impl Drop for ReadyEventSet {
     fn drop(&mut self) {
         if let Some(ref mut es) = self.event_set.take() {
+            overflow::unregister(*es, self.overflow_thread);
             unsafe {
-                check(ffi::PAPI_cleanup_eventset(*es)).expect("Failed to cleanup PAPI event set");
-                check(ffi::PAPI_destroy_eventset(es)).expect("Failed to destroy PAPI event set");
+                check_cleanup(
+                    ffi::PAPI_cleanup_eventset(*es),
+                    "Failed to cleanup PAPI event set",
+                );
+                check_cleanup(
+                    ffi::PAPI_destroy_eventset(es),
+                    "Failed to destroy PAPI event set",
+                );
             }
         }
     }
 }
 
-impl Sample {
-    /// Converts a PAPI event code to a code name string.
-    pub(crate) fn event_code_to_name(event_code: i32) -> Result<String> {
-        let mut c_event_name = [0_u8; ffi::PAPI_MAX_STR_LEN as usize];
+impl RunningEventSet {
+    /// Accumulates the hardware events sepecified by the event set onto the
+    /// given `Sample`.
+    ///
+    /// The hardware counters are reset and continue running after the
+    /// accumulation.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     #
+    ///     let mut sample = Sample::default();
+    ///     ready_event_set.init_sample(&mut sample)?;
+    ///     let running_event_set = ready_event_set.start()?;
+    ///
+    ///     running_event_set.accum(&mut sample)?;
+    ///     #
+    ///     # running_event_set.stop(&mut sample)?;
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn accum(&self, sample: &mut Sample) -> Result<()> {
+        let event_set = self
+            .event_set
+            .expect("EventSet uninitialized; looks like a bug");
 
-        unsafe {
-            check(ffi::PAPI_event_code_to_name(
-                event_code,
-                c_event_name.as_mut_ptr() as *mut u8 as *mut c_char,
-            ))?;
+        self.check_sample(sample)?;
+
+        if !self.paused.get() {
+            with_name(
+                unsafe { check(ffi::PAPI_accum(event_set, sample.values.as_mut_ptr())) },
+                &self.name,
+                "accumulate",
+            )?;
         }
-        let nul_index = c_event_name
-            .iter()
-            .position(|&byte| byte == 0)
-            .expect("Couldn't find '\0' byte in PAPI event codename");
 
-        Ok(unsafe { String::from_utf8_unchecked(c_event_name[0..nul_index].to_vec()) })
+        self.last_read_values.borrow_mut().fill(0);
+        let mut paused_values = self.paused_values.borrow_mut();
+        sample
+            .values
+            .iter_mut()
+            .zip(paused_values.iter_mut())
+            .for_each(|(value, paused)| *value += mem::take(paused));
+
+        Ok(())
     }
-}
 
-impl fmt::Display for Sample {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // Get event_info_t and convert i8 array into UTF8 String
-        let event_symbols = self
-            .event_codes
-            .iter()
-            .map(|&code| Self::event_code_to_name(code).map_err(|_| fmt::Error::default()))
-            .collect::<std::result::Result<Vec<String>, fmt::Error>>()?;
+    /// Reads the hardware events sepecified by the event set.
+    ///
+    /// The hardware counters continue running after the read.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     #
+    ///     let mut sample = Sample::default();
+    ///     ready_event_set.init_sample(&mut sample)?;
+    ///     let running_event_set = ready_event_set.start()?;
+    ///
+    ///     running_event_set.read(&mut sample)?;
+    ///     #
+    ///     # running_event_set.stop(&mut sample)?;
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn read(&self, sample: &mut Sample) -> Result<()> {
+        let event_set = self
+            .event_set
+            .expect("EventSet uninitialized; looks like a bug");
 
-        // Print the event symbols
-        event_symbols
-            .iter()
-            .zip(self.values.iter())
-            .try_for_each(|(symbol, sample)| write!(f, "{}: {} ", symbol, sample))
-    }
-}
+        self.check_sample(sample)?;
 
-impl Default for Sample {
-    fn default() -> Self {
-        Sample {
-            event_set_hash: Default::default(),
-            event_codes: Vec::new(),
-            values: Vec::new(),
+        if self.paused.get() {
+            sample.values.fill(0);
+        } else {
+            with_name(
+                unsafe { check(ffi::PAPI_read(event_set, sample.values.as_mut_ptr())) },
+                &self.name,
+                "read",
+            )?;
         }
-    }
-}
-
-impl IntoIterator for Sample {
-    type Item = (String, i64);
-    type IntoIter = ::std::iter::Zip<::std::vec::IntoIter<String>, ::std::vec::IntoIter<i64>>;
+        self.add_paused_values(sample);
 
-    fn into_iter(self) -> Self::IntoIter {
-        let event_names: Vec<_> = self
-            .event_codes
-            .into_iter()
-            .map(|code| {
-                Self::event_code_to_name(code)
-                    .expect("Failed to convert event code into event name string")
-            })
-            .collect();
-        event_names.into_iter().zip(self.values.into_iter())
+        Ok(())
     }
-}
-
-#[cfg(test)]
-mod tests {
 
-    use super::*;
-    use std::fmt::Write;
+    /// Reads the hardware events into a new sample.
+    ///
+    /// In contrast to `read`, the sample doesn't have to be initialized
+    /// beforehand. This trades an allocation per call for convenience. The
+    /// hardware counters continue running after the read.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     #
+    ///     let running_event_set = ready_event_set.start()?;
+    ///
+    ///     let mut sample = running_event_set.snapshot()?;
+    ///     println!("{}", sample);
+    ///     #
+    ///     # running_event_set.stop(&mut sample)?;
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn snapshot(&self) -> Result<Sample> {
+        let mut sample = Sample::default();
+        self.init_sample(&mut sample)?;
+        self.read(&mut sample)?;
 
-    #[test]
-    fn complete_pipeline() {
-        let papi = Papi::init().unwrap();
-        let event_added = EventSetBuilder::new(&papi)
-            .unwrap()
-            .add_event_by_name("CPU_CLK_UNHALTED");
-        assert!(event_added.is_ok());
+        Ok(sample)
+    }
 
-        let builder = event_added.unwrap();
-        let ready_event_set = builder.build().unwrap();
-        let mut sample = Sample::default();
-        ready_event_set.init_sample(&mut sample).unwrap();
-        let running = ready_event_set.start().unwrap();
-        running.stop(&mut sample).unwrap();
+    /// Reads the change of the hardware events since the previous
+    /// `read_delta`.
+    ///
+    /// The first call after `start` reads the change since the start. The
+    /// hardware counters continue running after the read. Calls to `accum`
+    /// and `reset` also reset the baseline of the next delta.
+    ///
+    /// A counter that decreases between two reads is assumed to have wrapped
+    /// around (see `counter_delta`).
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     #
+    ///     let mut sample = Sample::default();
+    ///     ready_event_set.init_sample(&mut sample)?;
+    ///     let running_event_set = ready_event_set.start()?;
+    ///
+    ///     for _ in 0..10 {
+    ///         let _sum: u32 = (0..100).sum();
+    ///         running_event_set.read_delta(&mut sample)?;
+    ///     }
+    ///     #
+    ///     # running_event_set.stop(&mut sample)?;
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn read_delta(&self, sample: &mut Sample) -> Result<()> {
+        self.read(sample)?;
 
-        let mut buffer = String::new();
-        write!(&mut buffer, "{}", &sample).unwrap();
+        let mut last_read_values = self.last_read_values.borrow_mut();
+        sample
+            .values
+            .iter_mut()
+            .zip(last_read_values.iter_mut())
+            .for_each(|(value, last)| {
+                let current = *value;
+                *value = counter_delta(current, *last);
+                *last = current;
+            });
 
-        let _all: Vec<(String, i64)> = sample.into_iter().collect();
+        Ok(())
     }
 
-    #[test]
-    #[ignore]
-    fn run_two_event_set_instances() {
+    /// Converts the event set into an iterator that samples it periodically.
+    ///
+    /// Each call to `next` sleeps until the end of the current period, and
+    /// then reads the change of the counters since the previous read (see
+    /// `read_delta`) into a new sample. The sample is returned together with
+    /// the time of the read. If a read takes longer than the period, the next
+    /// read starts without sleeping, and the following periods are aligned to
+    /// that read instead of catching up.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use std::time::Duration;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     #
+    ///     let running_event_set = ready_event_set.start()?;
+    ///
+    ///     for reading in running_event_set.sample_stream(Duration::from_millis(10)).take(3) {
+    ///         let (time, sample) = reading?;
+    ///         println!("{:?}: {}", time, sample);
+    ///     }
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn sample_stream(self, period: Duration) -> SampleStream {
+        SampleStream {
+            running: self,
+            period,
+            deadline: Instant::now() + period,
+        }
+    }
+
+    /// Initializes a `Sample` for use with the current event set.
+    ///
+    /// Typically, samples are initialized with `ReadyEventSet::init_sample`.
+    /// This method is required for samples of an event set whose events
+    /// were replaced with `replace_event`.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     #
+    ///     let running_event_set = ready_event_set.start()?;
+    ///
+    ///     let mut sample = Sample::default();
+    ///     running_event_set.init_sample(&mut sample)?;
+    ///     #
+    ///     # running_event_set.stop(&mut sample)?;
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn init_sample(&self, sample: &mut Sample) -> Result<()> {
+        let event_set = self
+            .event_set
+            .expect("EventSet uninitialized; looks like a bug");
+
+        init_sample(
+            event_set,
+            self.event_set_hash,
+            self.num_events,
+            &self.name,
+            &self.library,
+            sample,
+        )
+    }
+
+    /// Replaces an event of the event set with another event.
+    ///
+    /// Stops the event set, removes the old event, adds the new event, and
+    /// restarts the event set. The other events remain in the event set, but
+    /// all counters restart from zero.
+    ///
+    /// As the events change, samples of the previous event set become
+    /// invalid. Re-initialize them with `init_sample` before further use.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("PAPI_TOT_INS")?
+    ///     #     .build()?;
+    ///     #
+    ///     let running_event_set = ready_event_set.start()?;
+    ///     let running_event_set = running_event_set.replace_event("PAPI_TOT_INS", "PAPI_TOT_CYC")?;
+    ///
+    ///     let mut sample = Sample::default();
+    ///     running_event_set.init_sample(&mut sample)?;
+    ///     running_event_set.stop(&mut sample)?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn replace_event(mut self, old: &str, new: &str) -> Result<Self> {
+        let event_set = self
+            .event_set
+            .expect("EventSet uninitialized; looks like a bug");
+        let old_code = event_name_to_code(old)?;
+        let new_code = event_name_to_code(new)?;
+
+        let stopped = if self.paused.get() {
+            Ok(())
+        } else {
+            unsafe { check(ffi::PAPI_stop(event_set, ptr::null_mut())) }
+        };
+        let replaced = unsafe {
+            stopped
+                .and_then(|_| check(ffi::PAPI_remove_event(event_set, old_code)))
+                .and_then(|_| check(ffi::PAPI_add_event(event_set, new_code)))
+        };
+        with_name(replaced, &self.name, "replace event")?;
+
+        let event_codes = list_event_codes(event_set, self.num_events.get())?;
+        self.event_set_hash = hash_event_codes(&event_codes);
+        self.paused.set(false);
+        self.paused_values.borrow_mut().fill(0);
+        self.last_read_values.borrow_mut().fill(0);
+
+        self.start_nsec = self.timer_source.now_nsec();
+        with_name(
+            unsafe { check(ffi::PAPI_start(event_set)) },
+            &self.name,
+            "restart",
+        )?;
+
+        Ok(self)
+    }
+
+    /// Resets the hardware counters of the event set to zero.
+    ///
+    /// The hardware counters continue running after the reset.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     #
+    ///     let mut sample = Sample::default();
+    ///     ready_event_set.init_sample(&mut sample)?;
+    ///     let running_event_set = ready_event_set.start()?;
+    ///
+    ///     running_event_set.reset()?;
+    ///     #
+    ///     # running_event_set.stop(&mut sample)?;
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn reset(&self) -> Result<()> {
+        let event_set = self
+            .event_set
+            .expect("EventSet uninitialized; looks like a bug");
+
+        self.paused_values.borrow_mut().fill(0);
+        self.last_read_values.borrow_mut().fill(0);
+        with_name(
+            unsafe { check(ffi::PAPI_reset(event_set)) },
+            &self.name,
+            "reset",
+        )
+    }
+
+    /// Pauses counting the hardware events without stopping the event set.
+    ///
+    /// The counts up to the pause are kept, and are included in subsequent
+    /// reads. Events that occur while the event set is paused are not
+    /// counted. Call `resume` to continue counting.
+    ///
+    /// Internally, pausing stops the PAPI event set, and resuming restarts it.
+    /// Thus, pause and resume have a similar overhead as `stop` and `start`.
+    /// The duration returned by `stop_timed` includes the paused intervals.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     #
+    ///     let mut sample = Sample::default();
+    ///     ready_event_set.init_sample(&mut sample)?;
+    ///     let running_event_set = ready_event_set.start()?;
+    ///
+    ///     running_event_set.pause()?;
+    ///     // Setup that shouldn't be counted
+    ///     running_event_set.resume()?;
+    ///
+    ///     running_event_set.stop(&mut sample)?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn pause(&self) -> Result<()> {
+        let event_set = self
+            .event_set
+            .expect("EventSet uninitialized; looks like a bug");
+
+        if self.paused.get() {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Event set {} is already paused",
+                self.name
+            )))?;
+        }
+
+        let mut values = vec![0; self.num_events.get() as usize];
+        with_name(
+            unsafe { check(ffi::PAPI_stop(event_set, values.as_mut_ptr())) },
+            &self.name,
+            "pause",
+        )?;
+
+        self.paused_values
+            .borrow_mut()
+            .iter_mut()
+            .zip(values.iter())
+            .for_each(|(paused, value)| *paused += value);
+        self.paused.set(true);
+
+        Ok(())
+    }
+
+    /// Resumes counting the hardware events after a `pause`.
+    ///
+    /// The counters continue from the counts at the time of the pause.
+    pub fn resume(&self) -> Result<()> {
+        let event_set = self
+            .event_set
+            .expect("EventSet uninitialized; looks like a bug");
+
+        if !self.paused.get() {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Event set {} is not paused",
+                self.name
+            )))?;
+        }
+
+        with_name(
+            unsafe { check(ffi::PAPI_start(event_set)) },
+            &self.name,
+            "resume",
+        )?;
+        self.paused.set(false);
+
+        Ok(())
+    }
+
+    /// Checks that the sample is initialized for the event set.
+    ///
+    /// PAPI writes one value per event into the sample. Thus, checking the
+    /// length guards against out-of-bounds writes.
+    fn check_sample(&self, sample: &Sample) -> Result<()> {
+        if sample.event_set_hash != self.event_set_hash {
+            Err(ErrorKind::InvalidArgument(
+                "Sample is not initialized".into(),
+            ))?;
+        }
+
+        if sample.values.len() != usize::from(self.num_events.get()) {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Sample has {} values, but the event set has {} events",
+                sample.values.len(),
+                self.num_events
+            )))?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the counts up to the last pause onto the sample.
+    fn add_paused_values(&self, sample: &mut Sample) {
+        sample
+            .values
+            .iter_mut()
+            .zip(self.paused_values.borrow().iter())
+            .for_each(|(value, paused)| *value += paused);
+    }
+
+    /// Stops sampling the hardware events specified by the event set.
+    ///
+    /// Note that this method destroys the event set.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     #
+    ///     let mut sample = Sample::default();
+    ///     ready_event_set.init_sample(&mut sample)?;
+    ///     let running_event_set = ready_event_set.start()?;
+    ///
+    ///     running_event_set.stop(&mut sample)?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn stop(self, sample: &mut Sample) -> Result<()> {
+        self.stop_counters(sample)
+    }
+
+    /// Stops sampling, and returns the event set for another run.
+    ///
+    /// In contrast to `stop`, the event set is not destroyed. Thus, multiple
+    /// disjoint regions can be measured with the same event set. Samples of
+    /// the event set remain valid.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     #
+    ///     let mut sample = Sample::default();
+    ///     ready_event_set.init_sample(&mut sample)?;
+    ///
+    ///     let running_event_set = ready_event_set.start()?;
+    ///     let ready_event_set = running_event_set.stop_into_ready(&mut sample)?;
+    ///
+    ///     let running_event_set = ready_event_set.start()?;
+    ///     running_event_set.stop(&mut sample)?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn stop_into_ready(mut self, sample: &mut Sample) -> Result<ReadyEventSet> {
+        self.stop_counters(sample)?;
+
+        Ok(ReadyEventSet {
+            name: std::mem::take(&mut self.name),
+            event_set: self.event_set.take(),
+            event_set_hash: self.event_set_hash,
+            num_events: self.num_events,
+            options: self.options,
+            overflow_thread: self.overflow_thread,
+            timer_source: self.timer_source,
+            library: self.library.clone(),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Stops the hardware counters and reads their values.
+    pub(crate) fn stop_counters(&self, sample: &mut Sample) -> Result<()> {
+        let event_set = self
+            .event_set
+            .expect("EventSet uninitialized; looks like a bug");
+
+        self.check_sample(sample)?;
+
+        if self.paused.get() {
+            sample.values.fill(0);
+        } else {
+            with_name(
+                unsafe { check(ffi::PAPI_stop(event_set, sample.values.as_mut_ptr())) },
+                &self.name,
+                "stop",
+            )?;
+        }
+        self.add_paused_values(sample);
+
+        Ok(())
+    }
+
+    /// Stops sampling and returns the duration of the measurement.
+    ///
+    /// The duration is measured in nanoseconds since the event set was
+    /// started, using the timer configured by
+    /// `EventSetBuilder::timer_source`.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample, TimerSource};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let ready_event_set = EventSetBuilder::new(&papi)?
+    ///         .timer_source(TimerSource::Virtual)
+    ///         .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///         .build()?;
+    ///
+    ///     let mut sample = Sample::default();
+    ///     ready_event_set.init_sample(&mut sample)?;
+    ///     let running_event_set = ready_event_set.start()?;
+    ///
+    ///     let nanos = running_event_set.stop_timed(&mut sample)?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn stop_timed(self, sample: &mut Sample) -> Result<i64> {
+        let end_nsec = self.timer_source.now_nsec();
+        let start_nsec = self.start_nsec;
+
+        self.stop(sample)?;
+
+        Ok(end_nsec - start_nsec)
+    }
+
+    /// Stops the event set, destroys it, and releases its PAPI resources.
+    ///
+    /// The counter values are discarded. In contrast to dropping the event
+    /// set, this returns errors instead of panicking or calling the cleanup
+    /// error hook. Destroying the event set is attempted even if stopping it
+    /// fails.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     #
+    ///     let running_event_set = ready_event_set.start()?;
+    ///     running_event_set.destroy()?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn destroy(mut self) -> Result<()> {
+        match self.event_set.take() {
+            Some(es) => {
+                let stopped = unsafe {
+                    let mut state = 0;
+                    check(ffi::PAPI_state(es, &mut state)).and_then(|_| {
+                        if (state as u32 & ffi::PAPI_RUNNING) != 0 {
+                            check(ffi::PAPI_stop(es, ptr::null_mut()))
+                        } else {
+                            Ok(())
+                        }
+                    })
+                };
+
+                stopped.and(destroy_event_set(es, self.overflow_thread))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for RunningEventSet {
+    fn drop(&mut self) {
+        if let Some(ref mut es) = self.event_set.take() {
+            overflow::unregister(*es, self.overflow_thread);
+            unsafe {
+                let mut state = 0;
+                check_cleanup(
+                    ffi::PAPI_state(*es, &mut state),
+                    "Failed to get PAPI counter state",
+                );
+                if (state as u32 & ffi::PAPI_RUNNING) != 0 {
+                    check_cleanup(
+                        ffi::PAPI_stop(*es, ptr::null_mut()),
+                        "Failed to stop PAPI counters",
+                    );
+                }
+
+                check_cleanup(
+                    ffi::PAPI_cleanup_eventset(*es),
+                    "Failed to cleanup PAPI event set",
+                );
+                check_cleanup(
+                    ffi::PAPI_destroy_eventset(es),
+                    "Failed to destroy PAPI event set",
+                );
+            }
+        }
+    }
+}
+
+impl<'p> EventSetBuilder<'p> {
+    /// Creates a new EventSetBuilder.
+    ///
+    /// This requires an initialized PAPI instance.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let builder = EventSetBuilder::new(&papi)?;
+    ///     # assert!(builder.add_event_by_name("CPU_CLK_UNHALTED").is_ok());
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn new(papi: &'p Papi) -> Result<Self> {
+        let mut event_set = ffi::PAPI_NULL;
+
+        unsafe {
+            check(ffi::PAPI_create_eventset(&mut event_set))?;
+        }
+
+        Ok(Self {
+            papi,
+            name: String::new(),
+            event_set: Some(event_set),
+            num_events: 0,
+            options: EventSetOptions::default(),
+            overcommit: false,
+            timer_source: TimerSource::default(),
+            event_codes: Vec::new(),
+            fixed_counter_events: None,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Creates a new EventSetBuilder with capacity for a number of events.
+    ///
+    /// The builder lists the events of the event set when adding an event and
+    /// when building the event set. Reserving capacity for the expected
+    /// number of events avoids repeatedly growing the internal buffers.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let builder = EventSetBuilder::with_capacity(&papi, 4)?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn with_capacity(papi: &'p Papi, capacity: usize) -> Result<Self> {
+        let mut builder = Self::new(papi)?;
+        builder.event_codes.reserve(capacity);
+
+        Ok(builder)
+    }
+
+    /// Creates a new EventSetBuilder for the events of a component.
+    ///
+    /// By default, an event set is assigned to the component of its first
+    /// event. This assigns the event set to a component up front, e.g., to
+    /// the RAPL component (see `Papi::components`).
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let builder = EventSetBuilder::with_component(&papi, 0)?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn with_component(papi: &'p Papi, component_id: i32) -> Result<Self> {
+        let mut builder = Self::new(papi)?;
+
+        unsafe {
+            check(ffi::PAPI_assign_eventset_component(
+                builder.event_set.unwrap(),
+                component_id,
+            ))?;
+        }
+        builder.options.component = Some(component_id);
+
+        Ok(builder)
+    }
+
+    /// Labels the event set for logging and diagnostics.
+    ///
+    /// The label is carried over to the `ReadyEventSet` and `RunningEventSet`,
+    /// and is shown in their `Debug` output and in their error messages.
+    /// Samples initialized by the event set show the label as a prefix of
+    /// their `Display` output, e.g., `[cache-misses] PAPI_L1_DCM: 42`. By
+    /// default, event sets are unlabeled.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let ready_event_set = EventSetBuilder::new(&papi)?
+    ///         .with_label("cache-misses")
+    ///         .add_event_by_name("PAPI_L1_DCM")?
+    ///         .build()?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.name = label.into();
+        self
+    }
+
+    /// Finalizes the building of a new `ReadyEventSet`.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let builder = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?;
+    ///     #
+    ///     let ready_event_set = builder.build()?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn build(mut self) -> Result<ReadyEventSet> {
+        let num_events = NonZeroU16::new(self.num_events).ok_or_else(|| {
+            ErrorKind::InvalidArgument("Cannot create EventSet without events!".into())
+        })?;
+        let event_set = self
+            .event_set
+            .expect("EventSet uninitialized; looks like a bug");
+
+        list_event_codes_into(event_set, self.num_events, &mut self.event_codes)?;
+        let event_set_hash = hash_event_codes(&self.event_codes);
+
+        Ok(ReadyEventSet {
+            name: std::mem::take(&mut self.name),
+            event_set: self.event_set.take(),
+            event_set_hash,
+            num_events,
+            options: self.options,
+            overflow_thread: None,
+            timer_source: self.timer_source,
+            library: self.papi.library(),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Adds a hardware event specified by its name to the event set.
+    ///
+    /// Returns an `OutOfHardwareCounters` error if all hardware counters are
+    /// in use. Events that run on fixed-function counters, e.g.,
+    /// `PAPI_TOT_INS`, `PAPI_TOT_CYC`, and `PAPI_REF_CYC`, don't use up
+    /// general-purpose counters. Thus, they are not counted against the
+    /// limit. The check is skipped for multiplexed event sets and after
+    /// `allow_overcommit`.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let builder = EventSetBuilder::new(&papi)?;
+    ///     #
+    ///     builder.add_event_by_name("CPU_CLK_UNHALTED")?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn add_event_by_name(self, name: &str) -> Result<Self> {
+        let code = EventCode::from_code(event_name_to_code(name)?);
+        self.add_named_event(code, name)
+    }
+
+    /// Adds a hardware event specified by its code to the event set.
+    ///
+    /// The hardware counter limit is checked as in `add_event_by_name`.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     use papi::events::EventCode;
+    ///
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let builder = EventSetBuilder::new(&papi)?;
+    ///     let code = EventCode::from_name(&papi, "PAPI_TOT_INS")?;
+    ///     builder.add_event_by_code(code)?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn add_event_by_code(self, code: EventCode) -> Result<Self> {
+        let name = code.to_string();
+        self.add_named_event(code, &name)
+    }
+
+    /// Adds a hardware event specified by its name to the event set, after
+    /// checking that the event can be counted.
+    ///
+    /// Some events resolve to a code, but cannot be counted on the current
+    /// hardware. `add_event_by_name` only notices this when adding the event,
+    /// which results in a less descriptive error. This method queries the
+    /// event first, and returns an `EventNotFound` error if the event isn't
+    /// available. The additional query has a small cost; thus, prefer
+    /// `add_event_by_name` for events that are known to exist.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let builder = EventSetBuilder::new(&papi)?;
+    ///     #
+    ///     builder.try_add_event_by_name("PAPI_TOT_INS")?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn try_add_event_by_name(self, name: &str) -> Result<Self> {
+        let code = event_name_to_code(name)?;
+
+        let ret = unsafe { ffi::PAPI_query_event(code) };
+        if ret == ffi::PAPI_ENOEVNT {
+            Err(ErrorKind::EventNotFound(name.to_string()))?;
+        } else if ret != ffi::PAPI_OK as i32 {
+            Err(add_event_error(name, ret))?;
+        }
+
+        self.add_named_event(EventCode::from_code(code), name)
+    }
+
+    /// Adds a hardware event, and reports errors with the given event name.
+    fn add_named_event(mut self, code: EventCode, name: &str) -> Result<Self> {
+        let code = code.into();
+
+        // Check if there are enough hardware counters available before adding
+        // another event counter. Multiplexing lifts the hardware limit.
+        if !self.options.multiplexed && !self.overcommit {
+            self.check_capacity(code)?;
+        }
+
+        let ret = unsafe { ffi::PAPI_add_event(self.event_set.unwrap(), code) };
+        if ret != ffi::PAPI_OK as i32 {
+            Err(add_event_error(name, ret))?;
+        }
+
+        self.num_events += 1;
+
+        Ok(self)
+    }
+
+    /// Adds multiple hardware events specified by their names to the event
+    /// set.
+    ///
+    /// The events are added in order. Returns the error of the first event
+    /// that fails to be added.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let builder = EventSetBuilder::new(&papi)?;
+    ///     #
+    ///     builder.add_events_by_name(&["PAPI_TOT_INS", "PAPI_TOT_CYC"])?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn add_events_by_name<I, S>(self, names: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        names.into_iter().try_fold(self, |builder, name| {
+            builder.add_event_by_name(name.as_ref())
+        })
+    }
+
+    /// Adds the events from a preset to the event set.
+    ///
+    /// Presets are specified by the configuration file. If the preset names a
+    /// component, the event set is assigned to that component before adding
+    /// the events. Otherwise, the events are added to the CPU component.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     use papi::Config;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     let config_str = r#"
+    ///     [presets]
+    ///     Test1 = ["UOPS_RETIRED:ALL", "UOPS_RETIRED:STALL_CYCLES"]
+    ///     Test2 = ["UOPS_EXECUTED:CORE", "UOPS_EXECUTED:STALL_CYCLES"]
+    ///     Test3 = ["UOPS_EXECUTED:THREAD"]
+    ///     "#;
+    ///
+    ///     let config = Config::parse_str(&config_str)?;
+    ///     # let papi = Papi::init_with_config(config)?;
+    ///     # let builder = EventSetBuilder::new(&papi)?;
+    ///     builder.use_preset("Test1")?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn use_preset(mut self, name: &str) -> Result<Self> {
+        let maybe_config = match &self.papi.config {
+            Some(o) => &o.presets,
+            None => Err(ErrorKind::InvalidArgument("No configuration set".into()))?,
+        };
+
+        let maybe_val = match maybe_config {
+            Some(c) => c.get(name),
+            None => Err(ErrorKind::InvalidArgument("No presets configured".into()))?,
+        };
+
+        let preset = match maybe_val {
+            Some(v) => v,
+            None => Err(ErrorKind::InvalidArgument(format!(
+                "Preset {} doesn't exist",
+                name
+            )))?,
+        };
+
+        if let Some(component) = preset.component() {
+            self.options.component = Some(self.assign_preset_component(name, component)?);
+        }
+
+        self.add_events_by_name(preset.events())
+    }
+
+    /// Creates a new, distinct `EventSetBuilder` instance containing the same
+    /// events as the given `EventSetBuilder` instance.
+    ///
+    /// The cloned builder is independent of the original, i.e., adding an
+    /// event to one builder does not affect the other. The clone has the same
+    /// options as the original, i.e., the same component, process or CPU
+    /// attachment, inheritance, granularity, domain, and multiplexing.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let builder = EventSetBuilder::new(&papi)?
+    ///         .add_event_by_name("CPU_CLK_UNHALTED")?;
+    ///     let cloned_builder = builder.try_clone()?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn try_clone(&self) -> Result<Self> {
+        let event_set = self
+            .event_set
+            .expect("EventSet uninitialized; looks like a bug");
+        let mut num_events_ffi = self.num_events.into();
+        let mut event_codes = vec![0; self.num_events.into()];
+
+        let mut new_event_set = ffi::PAPI_NULL;
+
+        unsafe {
+            check(ffi::PAPI_create_eventset(&mut new_event_set))?;
+        }
+
+        let cloned = Self {
+            papi: self.papi,
+            name: self.name.clone(),
+            event_set: Some(new_event_set),
+            num_events: self.num_events,
+            options: self.options,
+            overcommit: self.overcommit,
+            timer_source: self.timer_source,
+            event_codes: Vec::with_capacity(self.event_codes.capacity()),
+            fixed_counter_events: self.fixed_counter_events.clone(),
+            phantom: PhantomData,
+        };
+
+        self.options.apply(new_event_set)?;
+
+        if !event_codes.is_empty() {
+            unsafe {
+                check(ffi::PAPI_list_events(
+                    event_set,
+                    event_codes.as_mut_ptr(),
+                    &mut num_events_ffi,
+                ))?;
+                check(ffi::PAPI_add_events(
+                    new_event_set,
+                    event_codes.as_mut_ptr(),
+                    event_codes.len() as i32,
+                ))?;
+            }
+        }
+
+        Ok(cloned)
+    }
+
+    /// Attaches the event set to another process or thread.
+    ///
+    /// The event set then measures the target instead of the calling thread.
+    /// The target must be traceable by the calling process, as defined by
+    /// `ptrace(2)`. Typically, this means that the target is a child process
+    /// or runs as the same user, and that `perf_event_paranoid` permits
+    /// attaching (see `Papi::check_perf_permissions`).
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let pid = unsafe { libc::getpid() };
+    ///     let ready_event_set = EventSetBuilder::new(&papi)?
+    ///         .attach(pid)?
+    ///         .add_event_by_name("PAPI_TOT_INS")?
+    ///         .build()?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn attach(mut self, pid: libc::pid_t) -> Result<Self> {
+        self.assign_default_component()?;
+        attach_process(self.event_set.unwrap(), pid)?;
+        self.options.attached_pid = Some(pid);
+
+        Ok(self)
+    }
+
+    /// Attaches the event set to a CPU.
+    ///
+    /// The event set then counts the events of all processes on the CPU
+    /// instead of the calling thread. This is required to read socket-wide
+    /// events, e.g., uncore or RAPL events, on many systems. Attaching to a
+    /// CPU typically requires `CAP_SYS_ADMIN` or a `perf_event_paranoid`
+    /// setting of 0 or lower (see `Papi::check_perf_permissions`).
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let ready_event_set = EventSetBuilder::new(&papi)?
+    ///         .set_cpu(0)?
+    ///         .add_event_by_name("PAPI_TOT_INS")?
+    ///         .build()?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn set_cpu(mut self, cpu: u32) -> Result<Self> {
+        self.assign_default_component()?;
+        attach_cpu(self.event_set.unwrap(), cpu)?;
+        self.options.attached_cpu = Some(cpu);
+
+        Ok(self)
+    }
+
+    /// Enables counter multiplexing for the event set.
+    ///
+    /// Multiplexing time-shares the hardware counters between the events.
+    /// Thus, the event set can contain more events than there are hardware
+    /// counters. In turn, the values are extrapolated from the time that each
+    /// event was scheduled on a counter (see `Sample::is_multiplexed`).
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let ready_event_set = EventSetBuilder::new(&papi)?
+    ///         .enable_multiplexing()?
+    ///         .add_event_by_name("PAPI_TOT_INS")?
+    ///         .add_event_by_name("PAPI_TOT_CYC")?
+    ///         .build()?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn enable_multiplexing(mut self) -> Result<Self> {
+        self.assign_default_component()?;
+        set_multiplex(self.event_set.unwrap())?;
+        self.options.multiplexed = true;
+
+        Ok(self)
+    }
+
+    /// Sets whether child processes and threads inherit the event set.
+    ///
+    /// With inheritance, the counts of threads and processes that are created
+    /// after starting the event set are included in the totals, e.g., the
+    /// worker threads of an OpenMP parallel region. Inheritance is only
+    /// supported by the `perf_event` component. Combine it with `attach` to
+    /// include the children of another process.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let ready_event_set = EventSetBuilder::new(&papi)?
+    ///         .set_inherit(true)?
+    ///         .add_event_by_name("PAPI_TOT_INS")?
+    ///         .build()?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn set_inherit(mut self, inherit: bool) -> Result<Self> {
+        self.assign_default_component()?;
+        set_inherit_option(self.event_set.unwrap(), inherit)?;
+        self.options.inherit = inherit;
+
+        Ok(self)
+    }
+
+    /// Sets the granularity of the event set.
+    ///
+    /// The granularity determines the scope of the counted events, e.g.,
+    /// whether the event set counts only the current thread or also the
+    /// sibling hyper-thread of the same core. Returns an error if the
+    /// component of the event set doesn't support the granularity.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Granularity};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let ready_event_set = EventSetBuilder::new(&papi)?
+    ///         .set_granularity(Granularity::Thread)?
+    ///         .add_event_by_name("PAPI_TOT_INS")?
+    ///         .build()?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn set_granularity(mut self, granularity: Granularity) -> Result<Self> {
+        self.assign_default_component()?;
+        set_granularity_option(self.event_set.unwrap(), granularity)?;
+        self.options.granularity = Some(granularity);
+
+        Ok(self)
+    }
+
+    /// Sets the domain of the event set.
+    ///
+    /// The domain determines the execution modes in which events are counted.
+    /// By default, PAPI only counts events in user mode. The domain must be
+    /// set before building the event set. Returns an error if the component
+    /// of the event set doesn't support the domain.
+    ///
+    /// Counting in kernel mode usually requires a `perf_event_paranoid`
+    /// setting of 1 or lower (see `Papi::check_perf_permissions`).
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{Domain, EventSetBuilder};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let ready_event_set = EventSetBuilder::new(&papi)?
+    ///         .set_domain(Domain::All)?
+    ///         .add_event_by_name("PAPI_TOT_INS")?
+    ///         .build()?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn set_domain(mut self, domain: Domain) -> Result<Self> {
+        self.assign_default_component()?;
+        set_domain_option(self.event_set.unwrap(), domain)?;
+        self.options.domain = Some(domain);
+
+        Ok(self)
+    }
+
+    /// Sets the timer that measures the duration of timed measurements.
+    ///
+    /// The timer is used by `RunningEventSet::stop_timed`. By default, the
+    /// real time is measured.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, TimerSource};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let builder = EventSetBuilder::new(&papi)?.timer_source(TimerSource::Virtual);
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn timer_source(mut self, source: TimerSource) -> Self {
+        self.timer_source = source;
+        self
+    }
+
+    /// Allows adding more events than the capacity check permits.
+    ///
+    /// The capacity check in `add_event_by_name` is a heuristic (see
+    /// `add_event_by_name`). If it rejects an event that fits on the
+    /// hardware, this disables the check. PAPI still rejects events that
+    /// cannot be scheduled when they are added.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let builder = EventSetBuilder::new(&papi)?.allow_overcommit();
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn allow_overcommit(mut self) -> Self {
+        self.overcommit = true;
+        self
+    }
+
+    /// Checks if there is a free hardware counter for an event.
+    ///
+    /// Events that are known to run on fixed-function counters are neither
+    /// checked nor counted against the general-purpose counters.
+    fn check_capacity(&mut self, code: i32) -> Result<()> {
+        let fixed_counter_events = self
+            .fixed_counter_events
+            .get_or_insert_with(fixed_counter_event_codes);
+        if fixed_counter_events.contains(&code) {
+            return Ok(());
+        }
+
+        let event_set = self.event_set.unwrap();
+        list_event_codes_into(event_set, self.num_events, &mut self.event_codes)?;
+        let num_general_events = self
+            .event_codes
+            .iter()
+            .filter(|c| !fixed_counter_events.contains(c))
+            .count();
+
+        // The event set is assigned to the component of its first event
+        let mut component = unsafe { ffi::PAPI_get_eventset_component(event_set) };
+        if component < 0 {
+            component = unsafe { ffi::PAPI_get_event_component(code) };
+            if component < 0 {
+                check(component)?;
+            }
+        }
+
+        let num_counters = unsafe { ffi::PAPI_num_cmp_hwctrs(component) };
+        if num_counters < 0 {
+            check(num_counters)?;
+        } else if num_general_events >= num_counters as usize {
+            Err(ErrorKind::OutOfHardwareCounters(
+                "Too many hardware events specified",
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Assigns the event set to the component of a preset, and returns the
+    /// component index.
+    ///
+    /// Returns an error if the event set is already assigned to a different
+    /// component.
+    fn assign_preset_component(&self, preset: &str, component: &str) -> Result<i32> {
+        let event_set = self.event_set.unwrap();
+        let component_id = component_index(component)?.ok_or_else(|| {
+            ErrorKind::InvalidArgument(format!(
+                "Component {} of preset {} doesn't exist",
+                component, preset
+            ))
+        })?;
+
+        match unsafe { ffi::PAPI_get_eventset_component(event_set) } {
+            assigned if assigned < 0 => unsafe {
+                check(ffi::PAPI_assign_eventset_component(
+                    event_set,
+                    component_id,
+                ))?;
+                Ok(component_id)
+            },
+            assigned if assigned == component_id => Ok(component_id),
+            _ => Err(ErrorKind::InvalidArgument(format!(
+                "Preset {} requires component {}, but the event set is assigned to another component",
+                preset, component
+            )))?,
+        }
+    }
+
+    /// Assigns the event set to the CPU component, unless the event set is
+    /// already assigned to a component.
+    ///
+    /// PAPI requires an assigned component before setting event set options.
+    /// The component is recorded, as the options of clones require it too.
+    fn assign_default_component(&mut self) -> Result<()> {
+        let event_set = self.event_set.unwrap();
+
+        let mut component = unsafe { ffi::PAPI_get_eventset_component(event_set) };
+        if component < 0 {
+            component = 0;
+            unsafe {
+                check(ffi::PAPI_assign_eventset_component(event_set, component))?;
+            }
+        }
+        self.options.component = Some(component);
+
+        Ok(())
+    }
+}
+
+impl Drop for EventSetBuilder<'_> {
+    fn drop(&mut self) {
+        if let Some(ref mut es) = self.event_set.take() {
+            unsafe {
+                check_cleanup(
+                    ffi::PAPI_cleanup_eventset(*es),
+                    "Failed to cleanup PAPI event set",
+                );
+                check_cleanup(
+                    ffi::PAPI_destroy_eventset(es),
+                    "Failed to destroy PAPI event set",
+                );
+            }
+        }
+    }
+}
+
+impl TimerSource {
+    /// Reads the current time of the timer in nanoseconds.
+    fn now_nsec(self) -> i64 {
+        unsafe {
+            match self {
+                TimerSource::Real => ffi::PAPI_get_real_nsec(),
+                TimerSource::Virtual => ffi::PAPI_get_virt_nsec(),
+            }
+        }
+    }
+}
+
+impl EventSetOptions {
+    /// Applies the options to a new event set.
+    ///
+    /// The component is assigned first, as PAPI requires it before setting
+    /// other options. Multiplexing is enabled last.
+    fn apply(&self, event_set: i32) -> Result<()> {
+        if let Some(component) = self.component {
+            unsafe {
+                check(ffi::PAPI_assign_eventset_component(event_set, component))?;
+            }
+        }
+        if let Some(pid) = self.attached_pid {
+            attach_process(event_set, pid)?;
+        }
+        if let Some(cpu) = self.attached_cpu {
+            attach_cpu(event_set, cpu)?;
+        }
+        if self.inherit {
+            set_inherit_option(event_set, true)?;
+        }
+        if let Some(granularity) = self.granularity {
+            set_granularity_option(event_set, granularity)?;
+        }
+        if let Some(domain) = self.domain {
+            set_domain_option(event_set, domain)?;
+        }
+        if self.multiplexed {
+            set_multiplex(event_set)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Granularity {
+    /// Returns the PAPI granularity code.
+    fn code(self) -> i32 {
+        let code = match self {
+            Granularity::Thread => ffi::PAPI_GRN_THR,
+            Granularity::Process => ffi::PAPI_GRN_PROC,
+            Granularity::ProcessGroup => ffi::PAPI_GRN_PROCG,
+            Granularity::System => ffi::PAPI_GRN_SYS,
+            Granularity::SystemCpu => ffi::PAPI_GRN_SYS_CPU,
+        };
+
+        code as i32
+    }
+}
+
+impl Domain {
+    /// Returns the PAPI domain code.
+    fn code(self) -> i32 {
+        let code = match self {
+            Domain::User => ffi::PAPI_DOM_USER,
+            Domain::Kernel => ffi::PAPI_DOM_KERNEL,
+            Domain::Other => ffi::PAPI_DOM_OTHER,
+            Domain::Supervisor => ffi::PAPI_DOM_SUPERVISOR,
+            Domain::All => ffi::PAPI_DOM_ALL,
+        };
+
+        code as i32
+    }
+
+    /// Tests if all modes of the domain are contained in a component's
+    /// available domains.
+    ///
+    /// `Domain::All` spans multiple modes, and thus requires all of them.
+    fn is_supported_by(self, available_domains: i32) -> bool {
+        available_domains & self.code() == self.code()
+    }
+}
+
+impl SampleStream {
+    /// Returns the running event set, e.g., to stop it.
+    pub fn into_inner(self) -> RunningEventSet {
+        self.running
+    }
+
+    /// Reads the change of the counters into a new sample.
+    fn read(&self) -> Result<Sample> {
+        let mut sample = Sample::default();
+        self.running.init_sample(&mut sample)?;
+        self.running.read_delta(&mut sample)?;
+
+        Ok(sample)
+    }
+}
+
+impl Iterator for SampleStream {
+    type Item = Result<(Instant, Sample)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let now = Instant::now();
+        if self.deadline > now {
+            thread::sleep(self.deadline - now);
+            self.deadline += self.period;
+        } else {
+            // Skip the sleep, and don't accumulate the delay
+            self.deadline = now + self.period;
+        }
+
+        Some(self.read().map(|sample| (Instant::now(), sample)))
+    }
+}
+
+impl DeltaReader {
+    /// Creates a new reader for a running event set.
+    ///
+    /// Reads the counters once to set the baseline of the first delta.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{DeltaReader, EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     #
+    ///     let mut reader = DeltaReader::new(ready_event_set.start()?)?;
+    ///
+    ///     for _ in 0..10 {
+    ///         println!("{}", reader.tick()?);
+    ///     }
+    ///
+    ///     let mut sample = Sample::default();
+    ///     let running_event_set = reader.into_inner();
+    ///     running_event_set.init_sample(&mut sample)?;
+    ///     running_event_set.stop(&mut sample)?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn new(running: RunningEventSet) -> Result<Self> {
+        let mut previous = Sample::default();
+        running.init_sample(&mut previous)?;
+        running.read(&mut previous)?;
+
+        let current = previous.clone();
+        let delta = previous.clone();
+
+        Ok(Self {
+            running,
+            previous,
+            current,
+            delta,
+        })
+    }
+
+    /// Reads the counters and returns the change since the previous read.
+    ///
+    /// The returned sample is overwritten by the next call. Clone it to keep
+    /// the values.
+    pub fn tick(&mut self) -> Result<&Sample> {
+        self.running.read(&mut self.current)?;
+
+        self.delta
+            .values
+            .iter_mut()
+            .zip(self.current.values.iter().zip(self.previous.values.iter()))
+            .for_each(|(delta, (&current, &previous))| *delta = counter_delta(current, previous));
+
+        mem::swap(&mut self.previous, &mut self.current);
+
+        Ok(&self.delta)
+    }
+
+    /// Returns the running event set, e.g., to stop it.
+    pub fn into_inner(self) -> RunningEventSet {
+        self.running
+    }
+}
+
+/// Measures a single event during a workload.
+///
+/// Builds an event set with the event, and measures the workload as
+/// `ReadyEventSet::measure`. Returns the result of the workload together with
+/// the sample.
+///
+///     # use std::error::Error;
+///     # use std::result::Result;
+///     # use papi::Papi;
+///     #
+///     # fn main() -> Result<(), Box<dyn Error>> {
+///     let papi = Papi::init()?;
+///     let (sum, sample) = papi::measure(&papi, "PAPI_TOT_CYC", || (0..100).sum::<u32>())?;
+///     #
+///     # Ok(())
+///     # }
+///
+pub fn measure<T, F>(papi: &Papi, event_name: &str, f: F) -> Result<(T, Sample)>
+where
+    F: FnOnce() -> T + UnwindSafe,
+{
+    EventSetBuilder::new(papi)?
+        .add_event_by_name(event_name)?
+        .build()?
+        .measure(f)
+}
+
+/// Measures a single event during a fallible workload.
+///
+/// Behaves like `measure`, but the workload returns a `Result`. Thus, the `?`
+/// operator can be used inside the workload. If the workload fails, the event
+/// set is stopped and destroyed, and the error is returned without a sample.
+///
+///     # use std::error::Error;
+///     # use std::result::Result;
+///     # use papi::Papi;
+///     #
+///     # fn main() -> Result<(), Box<dyn Error>> {
+///     let papi = Papi::init()?;
+///     let (code, sample) = papi::try_measure(&papi, "PAPI_TOT_CYC", || {
+///         let code = papi.event_code("PAPI_TOT_INS")?;
+///         Ok(code)
+///     })?;
+///     #
+///     # Ok(())
+///     # }
+///
+pub fn try_measure<T, F>(papi: &Papi, event_name: &str, f: F) -> Result<(T, Sample)>
+where
+    F: FnOnce() -> Result<T> + UnwindSafe,
+{
+    let (result, sample) = measure(papi, event_name, f)?;
+
+    Ok((result?, sample))
+}
+
+/// Attaches an event set to another process or thread.
+fn attach_process(event_set: i32, pid: libc::pid_t) -> Result<()> {
+    unsafe { check(ffi::PAPI_attach(event_set, pid as std::os::raw::c_ulong)) }.chain_err(|| {
+        format!(
+            "Failed to attach event set to process {}; the process must exist and be traceable",
+            pid
+        )
+    })
+}
+
+/// Attaches an event set to a CPU.
+fn attach_cpu(event_set: i32, cpu: u32) -> Result<()> {
+    unsafe {
+        let mut option: ffi::PAPI_option_t = std::mem::zeroed();
+        option.cpu.eventset = event_set;
+        option.cpu.cpu_num = cpu;
+        check(ffi::PAPI_set_opt(ffi::PAPI_CPU_ATTACH as i32, &mut option))
+    }
+    .chain_err(|| {
+        format!(
+            "Failed to attach event set to CPU {}; this typically requires CAP_SYS_ADMIN",
+            cpu
+        )
+    })
+}
+
+/// Enables multiplexing for an event set.
+fn set_multiplex(event_set: i32) -> Result<()> {
+    unsafe {
+        check(ffi::PAPI_multiplex_init())?;
+        check(ffi::PAPI_set_multiplex(event_set))
+    }
+}
+
+/// Sets whether child processes and threads inherit an event set.
+fn set_inherit_option(event_set: i32, inherit: bool) -> Result<()> {
+    unsafe {
+        let mut option: ffi::PAPI_option_t = std::mem::zeroed();
+        option.inherit.eventset = event_set;
+        option.inherit.inherit = if inherit {
+            ffi::PAPI_INHERIT_ALL as i32
+        } else {
+            ffi::PAPI_INHERIT_NONE as i32
+        };
+        check(ffi::PAPI_set_opt(ffi::PAPI_INHERIT as i32, &mut option))
+    }
+}
+
+/// Sets the granularity of an event set, if its component supports it.
+fn set_granularity_option(event_set: i32, granularity: Granularity) -> Result<()> {
+    unsafe {
+        let component = ffi::PAPI_get_eventset_component(event_set);
+        let info = ffi::PAPI_get_component_info(component);
+        if info.is_null() || (*info).available_granularities & granularity.code() == 0 {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Granularity {:?} is not supported by component {}",
+                granularity, component
+            )))?;
+        }
+
+        let mut option: ffi::PAPI_option_t = std::mem::zeroed();
+        option.granularity.eventset = event_set;
+        option.granularity.granularity = granularity.code();
+        check(ffi::PAPI_set_opt(ffi::PAPI_GRANUL as i32, &mut option))
+    }
+}
+
+/// Sets the domain of an event set, if its component supports it.
+fn set_domain_option(event_set: i32, domain: Domain) -> Result<()> {
+    unsafe {
+        let component = ffi::PAPI_get_eventset_component(event_set);
+        let info = ffi::PAPI_get_component_info(component);
+        if info.is_null() || !domain.is_supported_by((*info).available_domains) {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Domain {:?} is not supported by component {}",
+                domain, component
+            )))?;
+        }
+
+        let mut option: ffi::PAPI_option_t = std::mem::zeroed();
+        option.domain.eventset = event_set;
+        option.domain.domain = domain.code();
+        check(ffi::PAPI_set_opt(ffi::PAPI_DOMAIN as i32, &mut option))
+    }
+}
+
+/// Computes the change of a counter between two reads.
+///
+/// A decrease is treated as a wrap-around of the counter. PAPI doesn't report
+/// the width of hardware counters, thus counters are assumed to be 64 bits
+/// wide, and the difference is computed modulo 2^64. This covers unsigned
+/// 64-bit counters that exceed the range of `i64`. Narrower hardware counters
+/// are extended to 64 bits by the `perf_event` component, and thus don't wrap
+/// in practice.
+fn counter_delta(current: i64, previous: i64) -> i64 {
+    current.wrapping_sub(previous)
+}
+
+/// Calls a workload `n` times.
+///
+/// The workload is called dynamically to prevent inlining. Thus, the loop
+/// overhead is identical for all workloads.
+fn run_iterations(n: usize, f: &mut dyn FnMut()) {
+    for _ in 0..n {
+        std::hint::black_box(&mut *f)();
+    }
+}
+
+/// Initializes a `Sample` for use with an event set.
+fn init_sample(
+    event_set: i32,
+    event_set_hash: u64,
+    num_events: NonZeroU16,
+    name: &str,
+    library: &LibraryRef,
+    sample: &mut Sample,
+) -> Result<()> {
+    let mut num_events_ffi = num_events.get().into();
+    let num_events = num_events.get().into();
+
+    sample.event_set_hash = event_set_hash;
+    sample.name.clear();
+    sample.name.push_str(name);
+    if sample.library.is_none() {
+        sample.library = Some(library.clone());
+    }
+
+    let multiplexed = unsafe { ffi::PAPI_get_multiplex(event_set) };
+    if multiplexed < 0 {
+        check(multiplexed)?;
+    }
+    sample.multiplexed = multiplexed > 0;
+
+    sample.event_codes.clear();
+    sample.event_codes.resize(num_events, 0);
+
+    sample.values.clear();
+    sample.values.resize(num_events, 0);
+
+    unsafe {
+        check(ffi::PAPI_list_events(
+            event_set,
+            sample.event_codes.as_mut_ptr(),
+            &mut num_events_ffi,
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Cleans up and destroys an event set.
+///
+/// Destroying the event set is attempted even if cleaning it up fails, such
+/// that the event set is not leaked. Returns the first error.
+fn destroy_event_set(mut event_set: i32, overflow_thread: Option<thread::ThreadId>) -> Result<()> {
+    overflow::unregister(event_set, overflow_thread);
+
+    let cleaned_up = unsafe { check(ffi::PAPI_cleanup_eventset(event_set)) };
+    let destroyed = unsafe { check(ffi::PAPI_destroy_eventset(&mut event_set)) };
+
+    cleaned_up.and(destroyed)
+}
+
+/// Lists the event codes of an event set.
+fn list_event_codes(event_set: i32, num_events: u16) -> Result<Vec<i32>> {
+    let mut event_codes = Vec::new();
+    list_event_codes_into(event_set, num_events, &mut event_codes)?;
+
+    Ok(event_codes)
+}
+
+/// Lists the event codes of an event set into an existing buffer.
+///
+/// Reuses the allocation of the buffer.
+fn list_event_codes_into(
+    event_set: i32,
+    num_events: u16,
+    event_codes: &mut Vec<i32>,
+) -> Result<()> {
+    event_codes.clear();
+    if num_events == 0 {
+        return Ok(());
+    }
+
+    let mut num_events_ffi = num_events.into();
+    event_codes.resize(num_events.into(), 0);
+
+    unsafe {
+        check(ffi::PAPI_list_events(
+            event_set,
+            event_codes.as_mut_ptr(),
+            &mut num_events_ffi,
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Resolves the codes of the events that run on fixed-function counters.
+///
+/// Intel CPUs count retired instructions, core cycles, and reference cycles
+/// on dedicated counters. The heuristic recognizes the preset and native
+/// events of these counters by name. Other vendors' CPUs have no such
+/// counters, and thus no events are recognized. Unknown events are assumed
+/// to require a general-purpose counter.
+fn fixed_counter_event_codes() -> Vec<i32> {
+    let is_intel = matches!(hw_info(), Ok(info) if info.vendor == ffi::PAPI_VENDOR_INTEL as i32);
+    if !is_intel {
+        return Vec::new();
+    }
+
+    FIXED_COUNTER_EVENTS
+        .iter()
+        .filter_map(|name| event_name_to_code(name).ok())
+        .collect()
+}
+
+/// Hashes the event codes of an event set.
+///
+/// The hash identifies the samples that belong to the event set.
+fn hash_event_codes(event_codes: &[i32]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    event_codes.iter().for_each(|code| code.hash(&mut hasher));
+    hasher.finish()
+}
+
+/// Adds the event set name to the error of a failed action, if the event set
+/// is named.
+fn with_name<T>(result: Result<T>, name: &str, action: &str) -> Result<T> {
+    if name.is_empty() {
+        result
+    } else {
+        result.chain_err(|| format!("event set '{}' failed to {}", name, action))
+    }
+}
+
+impl Sample {
+    /// Creates a new sample with capacity for a number of events.
+    ///
+    /// `init_sample` reuses the allocation of a sample. Thus, a sample that
+    /// is reused for event sets of different sizes reallocates at most once
+    /// if it is created with the capacity of the largest event set.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     #
+    ///     let mut sample = Sample::with_capacity(16);
+    ///     ready_event_set.init_sample(&mut sample)?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn with_capacity(capacity: usize) -> Self {
+        Sample {
+            event_codes: Vec::with_capacity(capacity),
+            values: Vec::with_capacity(capacity),
+            ..Default::default()
+        }
+    }
+
+    /// Converts a PAPI event code to a code name string.
+    pub(crate) fn event_code_to_name(event_code: i32) -> Result<String> {
+        let mut c_event_name = [0_u8; ffi::PAPI_MAX_STR_LEN as usize];
+
+        unsafe {
+            check(ffi::PAPI_event_code_to_name(
+                event_code,
+                c_event_name.as_mut_ptr() as *mut u8 as *mut c_char,
+            ))?;
+        }
+        let nul_index = c_event_name
+            .iter()
+            .position(|&byte| byte == 0)
+            .expect("Couldn't find '\0' byte in PAPI event codename");
+
+        Ok(unsafe { String::from_utf8_unchecked(c_event_name[0..nul_index].to_vec()) })
+    }
+
+    /// Returns the label of the event set that initialized the sample.
+    ///
+    /// The label is empty if the event set is unlabeled (see
+    /// `EventSetBuilder::with_label`).
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns `true` if the sample was measured by a multiplexed event set.
+    ///
+    /// The values of a multiplexed event set are extrapolated from the time
+    /// that each event was scheduled on a hardware counter. Thus, they are
+    /// estimates rather than exact counts.
+    pub fn is_multiplexed(&self) -> bool {
+        self.multiplexed
+    }
+
+    /// Sums up all values of the sample.
+    ///
+    /// This is only meaningful if all events count the same kind of event,
+    /// e.g., per-core cache misses.
+    pub fn total(&self) -> i64 {
+        self.values.iter().sum()
+    }
+
+    /// Sums up the values of the named events.
+    ///
+    /// Returns an error if an event is not contained in the sample.
+    pub fn total_of(&self, names: &[&str]) -> Result<i64> {
+        names.iter().map(|name| self.value_by_name(name)).sum()
+    }
+
+    /// Divides the value of one event by the value of another event.
+    ///
+    /// Returns an error if an event is not contained in the sample, or if the
+    /// value of the denominator is zero.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let ready_event_set = EventSetBuilder::new(&papi)?
+    ///         .add_events_by_name(["PAPI_TOT_INS", "PAPI_TOT_CYC"])?
+    ///         .build()?;
+    ///
+    ///     let (_, sample) = ready_event_set.measure(|| (0..100).sum::<u32>())?;
+    ///     let ipc = sample.derived_ratio("PAPI_TOT_INS", "PAPI_TOT_CYC")?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn derived_ratio(&self, numerator: &str, denominator: &str) -> Result<f64> {
+        let numerator_value = self.value_by_name(numerator)?;
+        let denominator_value = self.value_by_name(denominator)?;
+
+        if denominator_value == 0 {
+            Err(ErrorKind::InvalidArgument(format!(
+                "Value of event {} is zero",
+                denominator
+            )))?;
+        }
+
+        Ok(numerator_value as f64 / denominator_value as f64)
+    }
+
+    /// Computes the relative difference of each event to a baseline sample.
+    ///
+    /// Returns `(self - baseline) / baseline` for each event together with
+    /// the event name, e.g., to quantify the error of a multiplexed event set
+    /// against a non-multiplexed baseline. Both samples must contain the same
+    /// events. Returns an error if a baseline value is zero.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let baseline_set = EventSetBuilder::new(&papi)?
+    ///         .add_event_by_name("PAPI_TOT_INS")?
+    ///         .build()?;
+    ///     let multiplexed_set = EventSetBuilder::new(&papi)?
+    ///         .enable_multiplexing()?
+    ///         .add_event_by_name("PAPI_TOT_INS")?
+    ///         .build()?;
+    ///
+    ///     let (_, baseline) = baseline_set.measure(|| (0..100).sum::<u32>())?;
+    ///     let (_, sample) = multiplexed_set.measure(|| (0..100).sum::<u32>())?;
+    ///     for (name, error) in sample.relative_difference(&baseline)? {
+    ///         println!("{}: {:+.2}%", name, error * 100.0);
+    ///     }
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn relative_difference(&self, baseline: &Sample) -> Result<Vec<(String, f64)>> {
+        if self.event_set_hash != baseline.event_set_hash {
+            Err(ErrorKind::InvalidArgument(
+                "Cannot compare samples of different events".into(),
+            ))?;
+        }
+
+        self.event_codes
+            .iter()
+            .zip(self.values.iter().zip(baseline.values.iter()))
+            .map(|(&code, (&value, &baseline_value))| {
+                let name = Self::event_code_to_name(code)?;
+                if baseline_value == 0 {
+                    Err(ErrorKind::InvalidArgument(format!(
+                        "Baseline value of event {} is zero",
+                        name
+                    )))?;
+                }
+
+                let difference = (value - baseline_value) as f64 / baseline_value as f64;
+                Ok((name, difference))
+            })
+            .collect()
+    }
+
+    /// Adds the values of another sample to this sample.
+    ///
+    /// Both samples must belong to the same event set, e.g., the results of
+    /// multiple runs of an event set or of its clones.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     #
+    ///     let mut total = Sample::default();
+    ///     ready_event_set.init_sample(&mut total)?;
+    ///
+    ///     for _ in 0..10 {
+    ///         let (_, sample) = ready_event_set.try_clone()?.measure(|| (0..100).sum::<u32>())?;
+    ///         total.try_add(&sample)?;
+    ///     }
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn try_add(&mut self, other: &Sample) -> Result<()> {
+        if self.event_set_hash != other.event_set_hash {
+            Err(ErrorKind::InvalidArgument(
+                "Cannot add samples of different event sets".into(),
+            ))?;
+        }
+
+        self.values
+            .iter_mut()
+            .zip(other.values.iter())
+            .for_each(|(value, other)| *value += other);
+        self.multiplexed |= other.multiplexed;
+
+        Ok(())
+    }
+
+    /// Clamps negative values to zero.
+    ///
+    /// Overhead-corrected values can be slightly negative due to measurement
+    /// noise (see `ReadyEventSet::measure_corrected`).
+    pub fn clamp_negative(&mut self) {
+        self.values
+            .iter_mut()
+            .for_each(|value| *value = (*value).max(0));
+    }
+
+    /// Looks up the value of an event in the sample.
+    ///
+    /// Returns an error if the event is not contained in the sample.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let ready_event_set = EventSetBuilder::new(&papi)?
+    ///         .add_event_by_name("PAPI_TOT_INS")?
+    ///         .build()?;
+    ///
+    ///     let (_, sample) = ready_event_set.measure(|| (0..100).sum::<u32>())?;
+    ///     let instructions = sample.value_by_name("PAPI_TOT_INS")?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn value_by_name(&self, name: &str) -> Result<i64> {
+        match self.index_of(name)? {
+            Some(i) => Ok(self.values[i]),
+            None => Err(ErrorKind::InvalidArgument(format!(
+                "Event {} is not in the sample",
+                name
+            )))?,
+        }
+    }
+
+    /// Looks up the value of an event in the sample.
+    ///
+    /// Returns `None` if the event is not contained in the sample, or if the
+    /// event name is unknown to PAPI.
+    pub fn get(&self, name: &str) -> Option<i64> {
+        self.index_of(name).ok()?.map(|i| self.values[i])
+    }
+
+    /// Collects the event names and values into a map.
+    ///
+    /// In contrast to `into_iter`, the sample is not consumed, and errors
+    /// when resolving the event names are returned instead of panicking.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("PAPI_TOT_INS")?
+    ///     #     .build()?;
+    ///     #
+    ///     let (_, sample) = ready_event_set.measure(|| (0..100).sum::<u32>())?;
+    ///     let values = sample.to_map()?;
+    ///     println!("{:?}", values);
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn to_map(&self) -> Result<HashMap<String, i64>> {
+        Ok(self.named_values()?.into_iter().collect())
+    }
+
+    /// Converts the sample into an iterator over the event names and values.
+    ///
+    /// In contrast to `into_iter`, which substitutes the hex code for event
+    /// names that cannot be resolved, this returns an error.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("PAPI_TOT_INS")?
+    ///     #     .build()?;
+    ///     #
+    ///     let (_, sample) = ready_event_set.measure(|| (0..100).sum::<u32>())?;
+    ///     for (name, value) in sample.try_into_iter()? {
+    ///         println!("{}: {}", name, value);
+    ///     }
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn try_into_iter(self) -> Result<impl Iterator<Item = (String, i64)>> {
+        Ok(self.named_values()?.into_iter())
+    }
+
+    /// Looks up the position of an event in the sample.
+    fn index_of(&self, name: &str) -> Result<Option<usize>> {
+        let code = event_name_to_code(name)?;
+        Ok(self.event_codes.iter().position(|&c| c == code))
+    }
+
+    /// Resolves the event names and pairs them with their values.
+    pub(crate) fn named_values(&self) -> Result<Vec<(String, i64)>> {
+        self.event_codes
+            .iter()
+            .zip(self.values.iter())
+            .map(|(&code, &value)| Ok((Self::event_code_to_name(code)?, value)))
+            .collect()
+    }
+
+    /// Returns the hash of the event set that initialized the sample.
+    pub(crate) fn event_set_hash(&self) -> u64 {
+        self.event_set_hash
+    }
+
+    /// Serializes the sample into a compact binary format.
+    ///
+    /// The format consists of the event set hash, the number of events, the
+    /// multiplexing flag, the event codes, and the values. All integers are
+    /// encoded in little-endian byte order. The format is intended for
+    /// persisting large numbers of samples within a tool, and is not stable
+    /// across versions of this crate.
+    ///
+    ///     # use papi::event_set::Sample;
+    ///     let bytes = Sample::default().to_bytes();
+    ///     let sample = Sample::from_bytes(&bytes).unwrap();
+    ///
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes =
+            Vec::with_capacity(SAMPLE_HEADER_LEN + self.event_codes.len() * SAMPLE_EVENT_LEN);
+
+        bytes.extend_from_slice(&self.event_set_hash.to_le_bytes());
+        bytes.extend_from_slice(&(self.event_codes.len() as u32).to_le_bytes());
+        bytes.push(self.multiplexed as u8);
+        self.event_codes
+            .iter()
+            .for_each(|code| bytes.extend_from_slice(&code.to_le_bytes()));
+        self.values
+            .iter()
+            .for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+
+        bytes
+    }
+
+    /// Deserializes a sample from the binary format of `to_bytes`.
+    ///
+    /// Returns an error if the bytes are truncated or have trailing data.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let invalid = || ErrorKind::InvalidArgument("Invalid sample bytes".into());
+
+        if bytes.len() < SAMPLE_HEADER_LEN {
+            Err(invalid())?;
+        }
+        let (header, body) = bytes.split_at(SAMPLE_HEADER_LEN);
+
+        let event_set_hash = u64::from_le_bytes(header[0..8].try_into().unwrap());
+        let num_events = u32::from_le_bytes(header[8..12].try_into().unwrap()) as usize;
+        let multiplexed = match header[12] {
+            0 => false,
+            1 => true,
+            _ => Err(invalid())?,
+        };
+
+        if body.len() != num_events * SAMPLE_EVENT_LEN {
+            Err(invalid())?;
+        }
+        let (codes, values) = body.split_at(num_events * mem::size_of::<i32>());
+
+        Ok(Sample {
+            event_set_hash,
+            event_codes: codes
+                .chunks_exact(mem::size_of::<i32>())
+                .map(|c| i32::from_le_bytes(c.try_into().unwrap()))
+                .collect(),
+            values: values
+                .chunks_exact(mem::size_of::<i64>())
+                .map(|v| i64::from_le_bytes(v.try_into().unwrap()))
+                .collect(),
+            multiplexed,
+            name: String::new(),
+            library: None,
+        })
+    }
+}
+
+impl fmt::Display for Sample {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Get event_info_t and convert i8 array into UTF8 String
+        let event_symbols = self.event_codes.iter().map(|&code| {
+            Self::event_code_to_name(code).unwrap_or_else(|_| format!("EVENT_{:#x}", code))
+        });
+
+        // Mark extrapolated values of multiplexed event sets
+        let estimate = if self.multiplexed { "~" } else { "" };
+
+        // Prefix the name of named event sets
+        if !self.name.is_empty() {
+            write!(f, "[{}] ", self.name)?;
+        }
+
+        // Print the event symbols
+        event_symbols
+            .zip(self.values.iter())
+            .try_for_each(|(symbol, sample)| write!(f, "{}: {}{} ", symbol, estimate, sample))
+    }
+}
+
+impl Default for Sample {
+    fn default() -> Self {
+        Sample {
+            event_set_hash: Default::default(),
+            event_codes: Vec::new(),
+            values: Vec::new(),
+            multiplexed: false,
+            name: String::new(),
+            library: None,
+        }
+    }
+}
+
+/// Serializes the sample as a map of event names to values.
+///
+/// Event names are portable across machines and PAPI versions, whereas event
+/// codes are not.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Sample {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeMap};
+
+        let mut map = serializer.serialize_map(Some(self.values.len()))?;
+        for (&code, value) in self.event_codes.iter().zip(self.values.iter()) {
+            let name = Self::event_code_to_name(code).map_err(S::Error::custom)?;
+            map.serialize_entry(&name, value)?;
+        }
+        map.end()
+    }
+}
+
+/// Deserializes the sample from a map of event names to values.
+///
+/// The event codes and the event set hash are reconstructed from the event
+/// names. Thus, PAPI must be initialized, and unknown event names are
+/// rejected.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Sample {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        struct SampleVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for SampleVisitor {
+            type Value = Sample;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of event names to values")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> std::result::Result<Sample, A::Error> {
+                use serde::de::Error;
+
+                let mut sample = Sample::default();
+                while let Some((name, value)) = map.next_entry::<String, i64>()? {
+                    let code = event_name_to_code(&name)
+                        .map_err(|e| A::Error::custom(format!("unknown event {}: {}", name, e)))?;
+                    sample.event_codes.push(code);
+                    sample.values.push(value);
+                }
+                // Keep the hash of a default sample, such that it round-trips
+                if !sample.event_codes.is_empty() {
+                    sample.event_set_hash = hash_event_codes(&sample.event_codes);
+                }
+
+                Ok(sample)
+            }
+        }
+
+        deserializer.deserialize_map(SampleVisitor)
+    }
+}
+
+impl IntoIterator for Sample {
+    type Item = (String, i64);
+    type IntoIter = ::std::iter::Zip<::std::vec::IntoIter<String>, ::std::vec::IntoIter<i64>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let event_names: Vec<_> = self
+            .event_codes
+            .into_iter()
+            .map(|code| {
+                Self::event_code_to_name(code).unwrap_or_else(|_| format!("EVENT_{:#x}", code))
+            })
+            .collect();
+        event_names.into_iter().zip(self.values.into_iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::fmt::Write;
+
+    #[test]
+    fn complete_pipeline() {
+        let papi = Papi::init().unwrap();
+        let event_added = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("CPU_CLK_UNHALTED");
+        assert!(event_added.is_ok());
+
+        let builder = event_added.unwrap();
+        let ready_event_set = builder.build().unwrap();
+        let mut sample = Sample::default();
+        ready_event_set.init_sample(&mut sample).unwrap();
+        let running = ready_event_set.start().unwrap();
+        running.stop(&mut sample).unwrap();
+
+        let mut buffer = String::new();
+        write!(&mut buffer, "{}", &sample).unwrap();
+
+        assert!(!sample.is_multiplexed());
+
+        let _all: Vec<(String, i64)> = sample.into_iter().collect();
+    }
+
+    #[test]
+    fn measure_each_iteration() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("CPU_CLK_UNHALTED")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let samples = ready_event_set
+            .measure_each(3, || {
+                let _sum: u32 = (0..1000).sum();
+            })
+            .unwrap();
+
+        assert_eq!(samples.len(), 3);
+        assert!(samples.iter().all(|s| s.values.iter().all(|&v| v > 0)));
+    }
+
+    #[test]
+    fn reset_running_event_set() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut first = Sample::default();
+        let mut second = Sample::default();
+        ready_event_set.init_sample(&mut first).unwrap();
+        ready_event_set.init_sample(&mut second).unwrap();
+        let running = ready_event_set.start().unwrap();
+
+        let _sum: u64 = std::hint::black_box(0..1_000_000).sum();
+        running.read(&mut first).unwrap();
+
+        running.reset().unwrap();
+        let _sum: u64 = std::hint::black_box(0..1_000).sum();
+        running.read(&mut second).unwrap();
+
+        assert!(second.values[0] > 0);
+        assert!(second.values[0] < first.values[0]);
+        running.stop(&mut first).unwrap();
+    }
+
+    #[test]
+    fn pause_and_resume_running_event_set() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_CYC")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut before = Sample::default();
+        let mut after = Sample::default();
+        ready_event_set.init_sample(&mut before).unwrap();
+        ready_event_set.init_sample(&mut after).unwrap();
+        let running = ready_event_set.start().unwrap();
+
+        let _sum: u64 = std::hint::black_box(0..1_000).sum();
+        running.read(&mut before).unwrap();
+
+        running.pause().unwrap();
+        let _sum: u64 = std::hint::black_box(0..10_000_000).sum();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        running.read(&mut after).unwrap();
+        assert!(running.pause().is_err());
+        running.resume().unwrap();
+        assert!(running.resume().is_err());
+
+        assert!(before.values[0] > 0);
+        assert!(after.values[0] >= before.values[0]);
+        assert!(after.values[0] - before.values[0] < 1_000_000);
+
+        running.stop(&mut after).unwrap();
+        assert!(after.values[0] >= before.values[0]);
+    }
+
+    #[test]
+    fn display_label_of_sample() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .with_label("cache")
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut sample = Sample::default();
+        ready_event_set.init_sample(&mut sample).unwrap();
+
+        assert_eq!(sample.name(), "cache");
+        assert!(sample.to_string().starts_with("[cache] PAPI_TOT_INS: "));
+    }
+
+    #[test]
+    fn reject_undersized_sample() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_events_by_name(["PAPI_TOT_INS", "PAPI_TOT_CYC"])
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut sample = Sample::default();
+        ready_event_set.init_sample(&mut sample).unwrap();
+        let mut undersized = sample.clone();
+        undersized.values.truncate(1);
+
+        let running = ready_event_set.start().unwrap();
+        let error = running.read(&mut undersized).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::InvalidArgument(..)));
+        assert!(running.accum(&mut undersized).is_err());
+
+        running.stop(&mut sample).unwrap();
+    }
+
+    #[test]
+    fn take_monotonic_snapshots() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+        let running = ready_event_set.start().unwrap();
+
+        let first = running.snapshot().unwrap();
+        let _sum: u64 = std::hint::black_box(0..1_000_000).sum();
+        let mut second = running.snapshot().unwrap();
+
+        assert!(first.values[0] > 0);
+        assert!(second.values[0] >= first.values[0]);
+
+        running.stop(&mut second).unwrap();
+    }
+
+    #[test]
+    fn read_deltas_of_running_event_set() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut first = Sample::default();
+        let mut second = Sample::default();
+        ready_event_set.init_sample(&mut first).unwrap();
+        ready_event_set.init_sample(&mut second).unwrap();
+        let running = ready_event_set.start().unwrap();
+
+        let _sum: u64 = std::hint::black_box(0..1_000_000).sum();
+        running.read_delta(&mut first).unwrap();
+
+        let _sum: u64 = std::hint::black_box(0..1_000_000).sum();
+        running.read_delta(&mut second).unwrap();
+
+        let (first, second) = (first.values[0] as f64, second.values[0] as f64);
+        assert!(first > 0.0);
+        assert!((first - second).abs() / first < 0.1);
+
+        let mut sample = Sample::default();
+        running.init_sample(&mut sample).unwrap();
+        running.stop(&mut sample).unwrap();
+    }
+
+    #[test]
+    fn set_thread_granularity() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .set_granularity(Granularity::Thread)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build();
+
+        assert!(ready_event_set.is_ok());
+    }
+
+    #[test]
+    fn set_all_domains() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .set_domain(Domain::All)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build();
+
+        assert!(ready_event_set.is_ok());
+    }
+
+    #[test]
+    fn reject_partially_supported_domain() {
+        let user_only = ffi::PAPI_DOM_USER as i32;
+        assert!(Domain::User.is_supported_by(user_only));
+        assert!(!Domain::Kernel.is_supported_by(user_only));
+        assert!(!Domain::All.is_supported_by(user_only));
+        assert!(Domain::All.is_supported_by(ffi::PAPI_DOM_ALL as i32));
+    }
+
+    #[test]
+    #[ignore]
+    fn set_start_values_of_event_set() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert!(ready_event_set.set_start_values(&[1, 2]).is_err());
+        ready_event_set.set_start_values(&[1_000_000]).unwrap();
+
+        let mut sample = Sample::default();
+        ready_event_set.init_sample(&mut sample).unwrap();
+        let running = ready_event_set.start().unwrap();
+        running.read(&mut sample).unwrap();
+        assert!(sample.values[0] >= 1_000_000);
+
+        running.stop(&mut sample).unwrap();
+    }
+
+    #[test]
+    fn start_event_set_on_another_thread() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        sender.send(ready_event_set).unwrap();
+
+        let sample = std::thread::spawn(move || {
+            let ready_event_set = receiver.recv().unwrap();
+            let (ready_event_set, _guard) = ready_event_set.rebind_to_current_thread().unwrap();
+
+            let ((), sample) = ready_event_set
+                .measure(|| {
+                    let _sum: u64 = std::hint::black_box(0..1_000_000).sum();
+                })
+                .unwrap();
+            sample
+        })
+        .join()
+        .unwrap();
+
+        assert!(sample.values[0] > 0);
+    }
+
+    #[test]
+    fn keep_options_when_rebinding_event_set() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .enable_multiplexing()
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let multiplexed = std::thread::spawn(move || {
+            let (ready_event_set, _guard) = ready_event_set.rebind_to_current_thread().unwrap();
+            unsafe { ffi::PAPI_get_multiplex(ready_event_set.event_set.unwrap()) }
+        })
+        .join()
+        .unwrap();
+
+        assert!(multiplexed > 0);
+    }
+
+    #[test]
+    fn reject_rebinding_event_set_with_foreign_overflow_handlers() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap()
+            .overflow("PAPI_TOT_INS", 100_000, |_, _| {})
+            .unwrap();
+
+        let rebound = std::thread::spawn(move || {
+            ready_event_set
+                .rebind_to_current_thread()
+                .map(|(_, _guard)| ())
+        })
+        .join()
+        .unwrap();
+
+        assert!(rebound.is_err());
+    }
+
+    #[test]
+    fn restart_stopped_event_set() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut first = Sample::default();
+        let mut second = Sample::default();
+        ready_event_set.init_sample(&mut first).unwrap();
+        ready_event_set.init_sample(&mut second).unwrap();
+
+        let running = ready_event_set.start().unwrap();
+        let _sum: u64 = std::hint::black_box(0..1_000_000).sum();
+        let ready_event_set = running.stop_into_ready(&mut first).unwrap();
+
+        let running = ready_event_set.start().unwrap();
+        let _sum: u64 = std::hint::black_box(0..1_000_000).sum();
+        running.stop(&mut second).unwrap();
+
+        assert!(first.values[0] > 0);
+        assert!(second.values[0] > 0);
+    }
+
+    #[test]
+    fn stream_samples() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let start = Instant::now();
+        let readings: Vec<_> = ready_event_set
+            .start()
+            .unwrap()
+            .sample_stream(Duration::from_millis(100))
+            .take(3)
+            .map(|reading| reading.unwrap())
+            .collect();
+
+        assert_eq!(readings.len(), 3);
+        assert!(readings[0].0 >= start + Duration::from_millis(100));
+        assert!(readings[2].0 >= start + Duration::from_millis(300));
+        assert!(readings.iter().all(|(_, sample)| sample.values[0] > 0));
+    }
+
+    #[test]
+    fn replace_event_of_running_event_set() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_CYC")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut old_sample = Sample::default();
+        ready_event_set.init_sample(&mut old_sample).unwrap();
+        let running = ready_event_set.start().unwrap();
+        let running = running
+            .replace_event("PAPI_TOT_CYC", "PAPI_BR_INS")
+            .unwrap();
+
+        assert!(running.read(&mut old_sample).is_err());
+
+        let mut sample = Sample::default();
+        running.init_sample(&mut sample).unwrap();
+        running.stop(&mut sample).unwrap();
+
+        let names: Vec<String> = sample.into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["PAPI_TOT_INS", "PAPI_BR_INS"]);
+    }
+
+    #[test]
+    #[ignore]
+    fn run_two_event_set_instances() {
+        let papi = Papi::init().unwrap();
+        let event_added = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("CPU_CLK_UNHALTED");
+        assert!(event_added.is_ok());
+
+        let builder = event_added.unwrap();
+        let ready_event_set = builder.build().unwrap();
+        let cloned_event_set = ready_event_set.try_clone().unwrap();
+
+        let mut sample_1 = Sample::default();
+        let mut sample_2 = Sample::default();
+        ready_event_set.init_sample(&mut sample_1).unwrap();
+        cloned_event_set.init_sample(&mut sample_2).unwrap();
+
+        let running = ready_event_set.start().unwrap();
+        let running_cloned = cloned_event_set.start().unwrap();
+
+        running.stop(&mut sample_1).unwrap();
+        running_cloned.stop(&mut sample_1).unwrap();
+    }
+
+    #[test]
+    fn destroy_event_sets() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("CPU_CLK_UNHALTED")
+            .unwrap()
+            .build()
+            .unwrap();
+        let cloned_event_set = ready_event_set.try_clone().unwrap();
+
+        assert!(cloned_event_set.destroy().is_ok());
+        assert!(ready_event_set.start().unwrap().destroy().is_ok());
+    }
+
+    #[test]
+    fn sum_sample_values() {
+        let _papi = Papi::init().unwrap();
+        let sample = Sample {
+            event_codes: vec![
+                event_name_to_code("PAPI_TOT_INS").unwrap(),
+                event_name_to_code("PAPI_TOT_CYC").unwrap(),
+            ],
+            values: vec![3, 4],
+            ..Sample::default()
+        };
+
+        assert_eq!(sample.total(), 7);
+        assert_eq!(sample.total_of(&["PAPI_TOT_CYC"]).unwrap(), 4);
+        assert_eq!(
+            sample.total_of(&["PAPI_TOT_INS", "PAPI_TOT_CYC"]).unwrap(),
+            7
+        );
+        assert!(sample.total_of(&["PAPI_L1_DCM"]).is_err());
+    }
+
+    #[test]
+    fn get_sample_values_by_name() {
+        let _papi = Papi::init().unwrap();
+        let sample = Sample {
+            event_codes: vec![event_name_to_code("PAPI_TOT_INS").unwrap()],
+            values: vec![3],
+            ..Sample::default()
+        };
+
+        assert_eq!(sample.value_by_name("PAPI_TOT_INS").unwrap(), 3);
+        assert!(sample.value_by_name("PAPI_TOT_CYC").is_err());
+
+        assert_eq!(sample.get("PAPI_TOT_INS"), Some(3));
+        assert_eq!(sample.get("PAPI_TOT_CYC"), None);
+        assert_eq!(sample.get("NOT_A_REAL_EVENT"), None);
+    }
+
+    #[test]
+    fn sample_to_map() {
+        let _papi = Papi::init().unwrap();
+        let sample = Sample {
+            event_codes: vec![
+                event_name_to_code("PAPI_TOT_INS").unwrap(),
+                event_name_to_code("PAPI_TOT_CYC").unwrap(),
+            ],
+            values: vec![3, 4],
+            ..Sample::default()
+        };
+
+        let map = sample.to_map().unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["PAPI_TOT_INS"], 3);
+        assert_eq!(map["PAPI_TOT_CYC"], 4);
+    }
+
+    #[test]
+    fn iterate_sample_with_invalid_event_code() {
+        let _papi = Papi::init().unwrap();
+        let sample = Sample {
+            event_codes: vec![0],
+            values: vec![3],
+            ..Sample::default()
+        };
+
+        assert!(sample.clone().try_into_iter().is_err());
+        assert_eq!(sample.to_string(), "EVENT_0x0: 3 ");
+        assert_eq!(
+            sample.into_iter().collect::<Vec<_>>(),
+            [("EVENT_0x0".to_string(), 3)]
+        );
+    }
+
+    #[test]
+    fn display_sample_after_dropping_papi() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut sample = Sample::default();
+        ready_event_set.init_sample(&mut sample).unwrap();
+        drop(ready_event_set);
+        drop(papi);
+
+        assert!(sample.to_string().starts_with("PAPI_TOT_INS: "));
+        assert_eq!(sample.to_map().unwrap()["PAPI_TOT_INS"], 0);
+    }
+
+    #[test]
+    fn wrap_counter_deltas() {
+        assert_eq!(counter_delta(10, 3), 7);
+        assert_eq!(counter_delta(i64::MIN, i64::MAX), 1);
+        assert_eq!(counter_delta(4, -1), 5);
+        assert_eq!(counter_delta(i64::MIN + 2, i64::MAX - 2), 5);
+    }
+
+    #[test]
+    fn divide_sample_values() {
+        let _papi = Papi::init().unwrap();
+        let sample = Sample {
+            event_codes: vec![
+                event_name_to_code("PAPI_TOT_INS").unwrap(),
+                event_name_to_code("PAPI_TOT_CYC").unwrap(),
+                event_name_to_code("PAPI_L1_DCM").unwrap(),
+            ],
+            values: vec![6, 4, 0],
+            ..Sample::default()
+        };
+
+        assert_eq!(
+            sample
+                .derived_ratio("PAPI_TOT_INS", "PAPI_TOT_CYC")
+                .unwrap(),
+            1.5
+        );
+        assert!(sample.derived_ratio("PAPI_TOT_INS", "PAPI_L1_DCM").is_err());
+        assert!(sample.derived_ratio("PAPI_TOT_INS", "PAPI_L2_DCM").is_err());
+        assert!(sample.derived_ratio("PAPI_L2_DCM", "PAPI_TOT_CYC").is_err());
+    }
+
+    #[test]
+    fn sample_bytes_round_trip() {
+        let sample = Sample {
+            event_set_hash: 0x0123_4567_89ab_cdef,
+            event_codes: vec![-2_147_483_648, 42],
+            values: vec![i64::MIN, 7],
+            multiplexed: true,
+            name: String::new(),
+            library: None,
+        };
+
+        let bytes = sample.to_bytes();
+        assert_eq!(bytes.len(), SAMPLE_HEADER_LEN + 2 * SAMPLE_EVENT_LEN);
+
+        let decoded = Sample::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.event_set_hash, sample.event_set_hash);
+        assert_eq!(decoded.event_codes, sample.event_codes);
+        assert_eq!(decoded.values, sample.values);
+        assert_eq!(decoded.multiplexed, sample.multiplexed);
+
+        assert!(Sample::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Sample::from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn build_with_capacity() {
+        let papi = Papi::init().unwrap();
+        let presets = papi.available_presets().unwrap();
+        let events: Vec<&str> = presets
+            .iter()
+            .filter(|preset| !preset.derived)
+            .map(|preset| preset.symbol.as_str())
+            .take(16)
+            .collect();
+        assert_eq!(events.len(), 16);
+
+        let ready_event_set = EventSetBuilder::with_capacity(&papi, 16)
+            .unwrap()
+            .enable_multiplexing()
+            .unwrap()
+            .add_events_by_name(events)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut sample = Sample::with_capacity(16);
+        ready_event_set.init_sample(&mut sample).unwrap();
+        assert_eq!(sample.values.len(), 16);
+    }
+
+    #[test]
+    #[ignore]
+    fn multiplex_more_events_than_counters() {
+        let papi = Papi::init().unwrap();
+        let num_counters = unsafe { ffi::PAPI_num_counters() };
+        assert!(num_counters > 0);
+
+        let events = [
+            "PAPI_TOT_INS",
+            "PAPI_TOT_CYC",
+            "PAPI_BR_INS",
+            "PAPI_BR_MSP",
+            "PAPI_L1_DCM",
+            "PAPI_L2_DCM",
+            "PAPI_L1_ICM",
+            "PAPI_TLB_DM",
+            "PAPI_LD_INS",
+            "PAPI_SR_INS",
+        ];
+        assert!(events.len() > num_counters as usize);
+
+        let ready_event_set = events
+            .iter()
+            .try_fold(
+                EventSetBuilder::new(&papi)
+                    .unwrap()
+                    .enable_multiplexing()
+                    .unwrap(),
+                |builder, event| builder.add_event_by_name(event),
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut sample = Sample::default();
+        ready_event_set.init_sample(&mut sample).unwrap();
+        let running = ready_event_set.start().unwrap();
+        let _sum: u64 = std::hint::black_box(0..1_000_000).sum();
+        running.stop(&mut sample).unwrap();
+
+        assert!(sample.is_multiplexed());
+        assert_eq!(sample.values.len(), events.len());
+    }
+
+    #[test]
+    fn read_deltas() {
         let papi = Papi::init().unwrap();
-        let event_added = EventSetBuilder::new(&papi)
+        let ready_event_set = EventSetBuilder::new(&papi)
             .unwrap()
-            .add_event_by_name("CPU_CLK_UNHALTED");
-        assert!(event_added.is_ok());
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut reader = DeltaReader::new(ready_event_set.start().unwrap()).unwrap();
+
+        let _sum: u64 = std::hint::black_box(0..1_000_000).sum();
+        let first = reader.tick().unwrap().values[0];
+
+        let _sum: u64 = std::hint::black_box(0..1_000).sum();
+        let second = reader.tick().unwrap().values[0];
+
+        assert!(first > 0);
+        assert!(second > 0);
+        assert!(second < first);
+
+        let running = reader.into_inner();
+        let mut sample = Sample::default();
+        running.init_sample(&mut sample).unwrap();
+        running.stop(&mut sample).unwrap();
+        assert!(sample.values[0] >= first + second);
+    }
+
+    #[test]
+    fn recognize_fixed_counter_events() {
+        let _papi = Papi::init().unwrap();
+        let fixed_counter_events = fixed_counter_event_codes();
+        let is_intel = hw_info().unwrap().vendor == ffi::PAPI_VENDOR_INTEL as i32;
+
+        assert_eq!(
+            fixed_counter_events.contains(&event_name_to_code("PAPI_TOT_INS").unwrap()),
+            is_intel
+        );
+        assert!(!fixed_counter_events.contains(&event_name_to_code("PAPI_BR_INS").unwrap()));
+    }
+
+    #[test]
+    fn count_overflows() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let papi = Papi::init().unwrap();
+        let overflows = Rc::new(Cell::new(0));
+        let counter = overflows.clone();
+
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap()
+            .overflow("PAPI_TOT_INS", 100_000, move |_, _| {
+                counter.set(counter.get() + 1)
+            })
+            .unwrap();
+
+        let (_, sample) = ready_event_set
+            .measure(|| std::hint::black_box(0..10_000_000_u64).sum::<u64>())
+            .unwrap();
+
+        assert!(sample.values[0] > 100_000);
+        assert!(overflows.get() > 0);
+    }
+
+    #[test]
+    fn overflow_event_not_in_event_set() {
+        let papi = Papi::init().unwrap();
+        let overflow = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap()
+            .overflow("PAPI_TOT_CYC", 100_000, |_, _| {});
+
+        assert!(overflow.is_err());
+    }
+
+    #[test]
+    fn stop_timed_event_set() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .timer_source(TimerSource::Virtual)
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(ready_event_set.timer_source, TimerSource::Virtual);
+
+        let mut sample = Sample::default();
+        ready_event_set.init_sample(&mut sample).unwrap();
+        let running = ready_event_set.start().unwrap();
+        let _sum: u64 = std::hint::black_box(0..1_000_000).sum();
+        let nanos = running.stop_timed(&mut sample).unwrap();
+
+        assert!(nanos > 0);
+    }
+
+    #[test]
+    fn measure_corrected_iterations() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let sample = ready_event_set
+            .measure_corrected(100, || {
+                let _sum: u64 = std::hint::black_box(0..10_000).sum();
+            })
+            .unwrap();
+
+        assert!(sample.values[0] > 0);
+    }
+
+    #[test]
+    fn add_samples() {
+        let mut total = Sample {
+            event_set_hash: 1,
+            event_codes: vec![10, 20],
+            values: vec![1, 2],
+            multiplexed: false,
+            name: String::new(),
+            library: None,
+        };
+        let other = Sample {
+            values: vec![3, 4],
+            ..total.clone()
+        };
+
+        total.try_add(&other).unwrap();
+        assert_eq!(total.values, vec![4, 6]);
+    }
+
+    #[test]
+    fn relative_difference_to_baseline() {
+        let _papi = Papi::init().unwrap();
+        let baseline = Sample {
+            event_set_hash: 1,
+            event_codes: vec![
+                event_name_to_code("PAPI_TOT_INS").unwrap(),
+                event_name_to_code("PAPI_TOT_CYC").unwrap(),
+            ],
+            values: vec![1000, 500],
+            multiplexed: false,
+            name: String::new(),
+            library: None,
+        };
+        let sample = Sample {
+            values: vec![1100, 500],
+            multiplexed: true,
+            ..baseline.clone()
+        };
+
+        let differences = sample.relative_difference(&baseline).unwrap();
+        assert_eq!(differences.len(), 2);
+        assert_eq!(differences[0].0, "PAPI_TOT_INS");
+        assert!((differences[0].1 - 0.1).abs() < 1e-9);
+        assert_eq!(differences[1].0, "PAPI_TOT_CYC");
+        assert_eq!(differences[1].1, 0.0);
+
+        let other = Sample {
+            event_set_hash: 2,
+            ..baseline.clone()
+        };
+        assert!(sample.relative_difference(&other).is_err());
+    }
+
+    #[test]
+    fn add_samples_of_different_event_sets() {
+        let mut total = Sample {
+            event_set_hash: 1,
+            event_codes: vec![10],
+            values: vec![1],
+            multiplexed: false,
+            name: String::new(),
+            library: None,
+        };
+        let other = Sample {
+            event_set_hash: 2,
+            ..total.clone()
+        };
+
+        assert!(total.try_add(&other).is_err());
+        assert_eq!(total.values, vec![1]);
+    }
+
+    #[test]
+    fn clamp_negative_values() {
+        let mut sample = Sample {
+            values: vec![-3, 0, 5],
+            ..Sample::default()
+        };
+        sample.clamp_negative();
+        assert_eq!(sample.values, vec![0, 0, 5]);
+    }
+
+    #[test]
+    #[ignore]
+    fn build_rapl_event_set() {
+        let papi = Papi::init().unwrap();
+        let rapl = papi
+            .components()
+            .unwrap()
+            .into_iter()
+            .find(|c| c.name == "rapl")
+            .expect("RAPL component is not available");
+
+        let ready_event_set = EventSetBuilder::with_component(&papi, rapl.index)
+            .unwrap()
+            .add_event_by_name("rapl:::PACKAGE_ENERGY:PACKAGE0")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut sample = Sample::default();
+        ready_event_set.init_sample(&mut sample).unwrap();
+        let running = ready_event_set.start().unwrap();
+        running.stop(&mut sample).unwrap();
+    }
+
+    #[test]
+    fn add_multiple_events() {
+        let papi = Papi::init().unwrap();
+        let builder = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_events_by_name(["PAPI_TOT_INS", "PAPI_TOT_CYC", "PAPI_BR_INS"])
+            .unwrap();
+
+        assert_eq!(builder.num_events, 3);
+    }
+
+    #[test]
+    fn add_unknown_event() {
+        let papi = Papi::init().unwrap();
+        let added = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("NOT_A_REAL_EVENT");
+
+        match added.unwrap_err().kind() {
+            ErrorKind::EventNotFound(name) => assert_eq!(name, "NOT_A_REAL_EVENT"),
+            other => panic!("Expected EventNotFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn list_event_names() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_events_by_name(["PAPI_TOT_INS", "PAPI_TOT_CYC"])
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            ready_event_set.event_names().unwrap(),
+            ["PAPI_TOT_INS", "PAPI_TOT_CYC"]
+        );
+    }
+
+    #[test]
+    fn add_duplicate_event() {
+        let papi = Papi::init().unwrap();
+        let added = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS");
+
+        let error = added.unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::CounterConflict));
+        assert!(error
+            .iter()
+            .any(|e| e.to_string().contains("'PAPI_TOT_INS'")));
+    }
+
+    #[test]
+    #[ignore]
+    fn query_unavailable_event() {
+        // PAPI_FMA_INS is not available on x86 processors
+        let papi = Papi::init().unwrap();
+        let added = EventSetBuilder::new(&papi)
+            .unwrap()
+            .try_add_event_by_name("PAPI_FMA_INS");
+
+        let error = added.unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::EventNotFound(..)));
+    }
+
+    #[test]
+    fn extract_papi_code_of_conflict() {
+        let papi = Papi::init().unwrap();
+        let added = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS");
+
+        assert_eq!(added.unwrap_err().papi_code(), Some(crate::ECNFLCT));
+    }
+
+    #[test]
+    fn display_add_event_errors() {
+        let error = crate::error::Error::from(ErrorKind::AddEventFailed(
+            "PAPI_L2_DCM".into(),
+            ffi::PAPI_ECNFLCT,
+        ));
+        assert!(error
+            .to_string()
+            .starts_with("failed to add event 'PAPI_L2_DCM': "));
+    }
+
+    #[test]
+    #[ignore]
+    fn inherit_counts_of_spawned_thread() {
+        let papi = Papi::init().unwrap();
+        let work = || {
+            let _sum: u64 = std::hint::black_box(0..10_000_000).sum();
+        };
+
+        let baseline = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+        let ((), baseline) = baseline.measure(work).unwrap();
+
+        let inherited = EventSetBuilder::new(&papi)
+            .unwrap()
+            .set_inherit(true)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+        let ((), inherited) = inherited
+            .measure(|| {
+                let worker = std::thread::spawn(work);
+                work();
+                worker.join().unwrap();
+            })
+            .unwrap();
+
+        assert!(inherited.values[0] > baseline.values[0]);
+    }
+
+    #[test]
+    #[ignore]
+    fn attach_to_cpu() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .set_cpu(0)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_CYC")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let ((), sample) = ready_event_set
+            .measure(|| std::thread::sleep(std::time::Duration::from_millis(10)))
+            .unwrap();
+        assert!(sample.values[0] > 0);
+    }
+
+    #[test]
+    fn attach_to_missing_process() {
+        let papi = Papi::init().unwrap();
+        let attached = EventSetBuilder::new(&papi).unwrap().attach(i32::MAX);
+
+        let err = attached.unwrap_err();
+        assert!(err.to_string().contains("attach event set to process"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let _papi = Papi::init().unwrap();
+        let event_codes = vec![
+            event_name_to_code("PAPI_TOT_INS").unwrap(),
+            event_name_to_code("PAPI_TOT_CYC").unwrap(),
+        ];
+        let sample = Sample {
+            event_set_hash: hash_event_codes(&event_codes),
+            event_codes,
+            values: vec![3, 4],
+            ..Sample::default()
+        };
+
+        let serialized = toml::to_string(&sample).unwrap();
+        assert!(serialized.contains("PAPI_TOT_INS = 3"));
+
+        let deserialized: Sample = toml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.event_codes, sample.event_codes);
+        assert_eq!(deserialized.values, sample.values);
+        assert_eq!(deserialized.event_set_hash, sample.event_set_hash);
+
+        let default = Sample::default();
+        let empty: Sample = toml::from_str(&toml::to_string(&default).unwrap()).unwrap();
+        assert!(empty.event_codes.is_empty());
+        assert!(empty.values.is_empty());
+        assert_eq!(empty.event_set_hash, default.event_set_hash);
+
+        let mut total = Sample::default();
+        assert!(total.try_add(&empty).is_ok());
+
+        assert!(toml::from_str::<Sample>("NOT_A_REAL_EVENT = 1").is_err());
+    }
+
+    #[test]
+    fn clone_multiple_event_sets() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("CPU_CLK_UNHALTED")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let cloned_event_sets = ready_event_set.try_clone_n(3).unwrap();
+        assert_eq!(cloned_event_sets.len(), 3);
+
+        let mut sample = Sample::default();
+        cloned_event_sets[0].init_sample(&mut sample).unwrap();
+        assert_eq!(sample.event_set_hash, ready_event_set.event_set_hash);
+    }
+
+    #[test]
+    fn clone_event_set_builder() {
+        let papi = Papi::init().unwrap();
+        let builder = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_CYC")
+            .unwrap();
+        let cloned_builder = builder.try_clone().unwrap();
 
-        let builder = event_added.unwrap();
         let ready_event_set = builder.build().unwrap();
-        let cloned_event_set = ready_event_set.try_clone().unwrap();
+        let cloned_event_set = cloned_builder.build().unwrap();
 
-        let mut sample_1 = Sample::default();
-        let mut sample_2 = Sample::default();
-        ready_event_set.init_sample(&mut sample_1).unwrap();
-        cloned_event_set.init_sample(&mut sample_2).unwrap();
+        let mut sample = Sample::default();
+        let mut cloned_sample = Sample::default();
+        ready_event_set.init_sample(&mut sample).unwrap();
+        cloned_event_set.init_sample(&mut cloned_sample).unwrap();
+
+        assert_eq!(sample.event_set_hash, cloned_sample.event_set_hash);
+        assert_eq!(sample.event_codes, cloned_sample.event_codes);
+        assert_eq!(sample.values, cloned_sample.values);
+    }
+
+    #[test]
+    fn clone_options_of_event_set_builder() {
+        let papi = Papi::init().unwrap();
+        let builder = EventSetBuilder::with_component(&papi, 0)
+            .unwrap()
+            .set_granularity(Granularity::Thread)
+            .unwrap()
+            .set_domain(Domain::User)
+            .unwrap()
+            .enable_multiplexing()
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap();
+        let cloned_builder = builder.try_clone().unwrap();
+
+        let cloned_event_set = cloned_builder.event_set.unwrap();
+        assert_eq!(
+            unsafe { ffi::PAPI_get_eventset_component(cloned_event_set) },
+            0
+        );
+        assert!(unsafe { ffi::PAPI_get_multiplex(cloned_event_set) } > 0);
+        assert_eq!(
+            cloned_builder.options.granularity,
+            Some(Granularity::Thread)
+        );
+        assert_eq!(cloned_builder.options.domain, Some(Domain::User));
+        assert!(cloned_builder.build().is_ok());
+    }
+
+    #[test]
+    fn clone_independent_event_set_builder() {
+        let papi = Papi::init().unwrap();
+        let builder = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap();
+        let cloned_builder = builder
+            .try_clone()
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_CYC")
+            .unwrap();
+
+        let ready_event_set = builder.build().unwrap();
+        let cloned_event_set = cloned_builder.build().unwrap();
+
+        assert_eq!(ready_event_set.num_events.get(), 1);
+        assert_eq!(cloned_event_set.num_events.get(), 2);
+        assert_ne!(
+            ready_event_set.event_set_hash,
+            cloned_event_set.event_set_hash
+        );
+    }
+
+    #[test]
+    fn measure_single_event() {
+        let papi = Papi::init().unwrap();
+        let (sum, sample) = measure(&papi, "PAPI_TOT_CYC", || {
+            std::hint::black_box(0..1_000_000_u64).sum::<u64>()
+        })
+        .unwrap();
+
+        assert_eq!(sum, 499_999_500_000);
+        assert!(sample.values[0] > 0);
+    }
+
+    #[test]
+    fn try_measure_single_event() {
+        let papi = Papi::init().unwrap();
+        let (sum, sample) = try_measure(&papi, "PAPI_TOT_CYC", || {
+            Ok(std::hint::black_box(0..1_000_000_u64).sum::<u64>())
+        })
+        .unwrap();
+
+        assert_eq!(sum, 499_999_500_000);
+        assert!(sample.values[0] > 0);
+
+        let failed = try_measure(&papi, "PAPI_TOT_CYC", || -> Result<()> {
+            Err(ErrorKind::InvalidArgument("failed workload".into()).into())
+        });
+        let error = failed.unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::InvalidArgument(..)));
+
+        // The failed measurement doesn't leave a running event set behind
+        let (_, sample) = measure(&papi, "PAPI_TOT_CYC", || ()).unwrap();
+        assert!(sample.values[0] > 0);
+    }
+
+    #[test]
+    fn measure_panicking_workload() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("CPU_CLK_UNHALTED")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let payload = panic::catch_unwind(|| {
+            ready_event_set.measure(|| {
+                let _sum: u32 = (0..1000).sum();
+                panic!("workload failed");
+            })
+        })
+        .unwrap_err();
+
+        let measurement_panic = payload.downcast::<MeasurementPanic>().unwrap();
+        assert_eq!(
+            measurement_panic.payload.downcast_ref::<&str>(),
+            Some(&"workload failed")
+        );
+        assert!(measurement_panic.sample.values.iter().all(|&v| v > 0));
+    }
+
+    #[test]
+    fn name_event_set() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .with_label("cycles")
+            .add_event_by_name("CPU_CLK_UNHALTED")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(format!("{:?}", ready_event_set).contains("cycles"));
 
         let running = ready_event_set.start().unwrap();
-        let running_cloned = cloned_event_set.start().unwrap();
+        assert!(format!("{:?}", running).contains("cycles"));
+    }
 
-        running.stop(&mut sample_1).unwrap();
-        running_cloned.stop(&mut sample_1).unwrap();
+    #[test]
+    fn name_event_set_errors() {
+        let named: Result<()> = Err(ErrorKind::PapiError(ffi::PAPI_EINVAL).into());
+        let error = with_name(named, "cycles", "start").unwrap_err();
+        assert_eq!(error.to_string(), "event set 'cycles' failed to start");
+
+        let unnamed: Result<()> = Err(ErrorKind::PapiError(ffi::PAPI_EINVAL).into());
+        let error = with_name(unnamed, "", "start").unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::PapiError(_)));
     }
 
     #[test]
@@ -0,0 +1,753 @@
+// Copyright 2019 German Research Center for Artificial Intelligence (DFKI)
+// Author: Clemens Lutz <clemens.lutz@dfki.de>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Discovery of the hardware events supported by PAPI components.
+//!
+//! PAPI distinguishes between preset events, which are portable across
+//! platforms, and native events, which are specific to a component (e.g., the
+//! CPU or RAPL). This module provides functions to enumerate native events at
+//! runtime.
+
+use super::error::{check, Error, ErrorKind, Result};
+use super::event_set::Sample;
+use super::ffi;
+use super::Papi;
+use std::convert::TryFrom;
+use std::ffi::CString;
+use std::fmt;
+use std::mem;
+use std::os::raw::{c_char, c_int};
+
+/// Modifies which native events are enumerated.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum EnumModifier {
+    /// Enumerates all base events, without their unit masks.
+    #[default]
+    All,
+    /// Enumerates all base events, followed by each of their unit masks.
+    Umasks,
+    /// Enumerates all base events, followed by all combinations of their unit
+    /// masks.
+    UmaskCombos,
+    /// Enumerates all base events, followed by the event groups they belong
+    /// to.
+    Groups,
+}
+
+/// The names of components that provide GPU events.
+const GPU_COMPONENTS: [&str; 2] = ["nvml", "cuda"];
+
+/// Descriptive information about an event.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EventInfo {
+    /// The event name
+    pub symbol: String,
+    /// A short description of the event
+    pub short_descr: String,
+    /// A long description of the event
+    pub long_descr: String,
+    /// The unit of the event values, e.g., "nJ" for RAPL energy events
+    pub units: String,
+    /// An additional note on the event
+    pub note: String,
+}
+
+/// A PAPI event code.
+///
+/// Event codes identify preset and native events. In contrast to a bare
+/// `i32`, an `EventCode` is known to refer to an event.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct EventCode(i32);
+
+/// Information about a native event.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NativeEventInfo {
+    /// The event code
+    pub code: i32,
+    /// The event name
+    pub symbol: String,
+    /// A long description of the event
+    pub long_descr: String,
+}
+
+/// Information about a PAPI component.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ComponentInfo {
+    /// The component index, as used to enumerate its events
+    pub index: i32,
+    /// The component name, e.g., `perf_event` or `rapl`
+    pub name: String,
+    /// The number of native events of the component
+    pub num_native_events: i32,
+    /// Whether the component is disabled, e.g., due to missing permissions
+    pub disabled: bool,
+    /// The reason why the component is disabled
+    pub disabled_reason: String,
+}
+
+/// Information about a preset event.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PresetInfo {
+    /// The event name
+    pub symbol: String,
+    /// A long description of the event
+    pub long_descr: String,
+    /// Whether the event is derived from multiple native events
+    pub derived: bool,
+}
+
+/// The bit mask of preset event codes.
+///
+/// PAPI defines the mask as a macro with a cast, which isn't translated into
+/// the bindings.
+const PAPI_PRESET_MASK: c_int = 0x8000_0000_u32 as c_int;
+
+/// The `derived` value of events that are not derived.
+const NOT_DERIVED: &str = "NOT_DERIVED";
+
+/// An iterator that enumerates the native events of a component.
+struct NativeEvents {
+    component: i32,
+    code: c_int,
+    /// The modifier of the next enumeration step, or `None` if the
+    /// enumeration has ended
+    modifier: Option<c_int>,
+}
+
+impl EnumModifier {
+    fn to_ffi(self) -> Option<c_int> {
+        match self {
+            EnumModifier::All => None,
+            EnumModifier::Umasks => Some(ffi::PAPI_NTV_ENUM_UMASKS as c_int),
+            EnumModifier::UmaskCombos => Some(ffi::PAPI_NTV_ENUM_UMASK_COMBOS as c_int),
+            EnumModifier::Groups => Some(ffi::PAPI_NTV_ENUM_GROUPS as c_int),
+        }
+    }
+}
+
+impl Papi {
+    /// Enumerates the native events of a component.
+    ///
+    /// The modifier selects whether unit masks or groups of each event are
+    /// enumerated in addition to the base events.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     use papi::Papi;
+    ///     use papi::events::EnumModifier;
+    ///
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     let papi = Papi::init()?;
+    ///     let events = papi.native_events(0, EnumModifier::Umasks)?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn native_events(&self, component: i32, modifier: EnumModifier) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        let mut code = ffi::PAPI_NATIVE_MASK;
+
+        let mut more = enum_cmp_event(&mut code, ffi::PAPI_ENUM_FIRST as c_int, component)?;
+        while more {
+            names.push(Sample::event_code_to_name(code)?);
+
+            if let Some(sub_modifier) = modifier.to_ffi() {
+                let mut sub_code = code;
+                while enum_cmp_event(&mut sub_code, sub_modifier, component)? {
+                    names.push(Sample::event_code_to_name(sub_code)?);
+                }
+            }
+
+            more = enum_cmp_event(&mut code, ffi::PAPI_ENUM_EVENTS as c_int, component)?;
+        }
+
+        Ok(names)
+    }
+
+    /// Lazily enumerates the native events of a component.
+    ///
+    /// In contrast to `native_events`, each event is enumerated only when the
+    /// iterator advances. Thus, the enumeration can be filtered or stopped
+    /// early without enumerating all events of the component. Unit masks and
+    /// groups are not enumerated. The iterator ends after the last event, or
+    /// after returning an error.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let first = papi
+    ///         .native_events_iter(0)
+    ///         .find(|event| event.as_ref().map_or(true, |e| e.symbol.contains("CYCLES")))
+    ///         .transpose()?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn native_events_iter(
+        &self,
+        component: i32,
+    ) -> impl Iterator<Item = Result<NativeEventInfo>> {
+        NativeEvents {
+            component,
+            code: ffi::PAPI_NATIVE_MASK,
+            modifier: Some(ffi::PAPI_ENUM_FIRST as c_int),
+        }
+    }
+
+    /// Lists the components of PAPI.
+    ///
+    /// Component 0 is the CPU component. Further components provide, e.g.,
+    /// RAPL energy counters or GPU events.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     for component in papi.components()? {
+    ///         println!("{}: {} events", component.name, component.num_native_events);
+    ///     }
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn components(&self) -> Result<Vec<ComponentInfo>> {
+        let num_components = unsafe { ffi::PAPI_num_components() };
+        if num_components < 0 {
+            check(num_components)?;
+        }
+
+        (0..num_components)
+            .map(|index| {
+                let info = unsafe { ffi::PAPI_get_component_info(index) };
+                if info.is_null() {
+                    check(ffi::PAPI_ENOCMP)?;
+                }
+                let info = unsafe { &*info };
+
+                Ok(ComponentInfo {
+                    index,
+                    name: c_chars_to_string(&info.name),
+                    num_native_events: info.num_native_events,
+                    disabled: info.disabled != 0,
+                    disabled_reason: c_chars_to_string(&info.disabled_reason),
+                })
+            })
+            .collect()
+    }
+
+    /// Enumerates the native events of the GPU components.
+    ///
+    /// Looks up the `nvml` and `cuda` components, and returns the names of
+    /// their events qualified by the component name, e.g.,
+    /// `nvml:::Tesla_V100-SXM2-16GB:power`. Returns an empty list if no GPU
+    /// component is present or enabled.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     for event in papi.gpu_events()? {
+    ///         println!("{}", event);
+    ///     }
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn gpu_events(&self) -> Result<Vec<String>> {
+        let mut events = Vec::new();
+
+        for &name in GPU_COMPONENTS.iter() {
+            let component = match component_index(name)? {
+                Some(c) => c,
+                None => continue,
+            };
+
+            let info = unsafe { ffi::PAPI_get_component_info(component) };
+            if info.is_null() || unsafe { (*info).disabled } != 0 {
+                continue;
+            }
+
+            events.extend(
+                self.native_events(component, EnumModifier::All)?
+                    .into_iter()
+                    .map(|event| qualify_event_name(name, event)),
+            );
+        }
+
+        Ok(events)
+    }
+
+    /// Lists the preset events that are available on this hardware.
+    ///
+    /// A preset is available if PAPI can map it to one or more native events
+    /// of the CPU.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     for preset in papi.available_presets()? {
+    ///         println!("{}: {}", preset.symbol, preset.long_descr);
+    ///     }
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn available_presets(&self) -> Result<Vec<PresetInfo>> {
+        let mut presets = Vec::new();
+        let mut code = PAPI_PRESET_MASK;
+
+        let mut more = enum_event(&mut code, ffi::PAPI_ENUM_FIRST as c_int)?;
+        while more {
+            let info = event_info(code)?;
+            if info.count > 0 {
+                presets.push(PresetInfo {
+                    symbol: c_chars_to_string(&info.symbol),
+                    long_descr: c_chars_to_string(&info.long_descr),
+                    derived: is_derived(&info),
+                });
+            }
+
+            more = enum_event(&mut code, ffi::PAPI_ENUM_EVENTS as c_int)?;
+        }
+
+        Ok(presets)
+    }
+
+    /// Resolves the event code of an event name.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let code = papi.event_code("PAPI_TOT_INS")?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn event_code(&self, name: &str) -> Result<i32> {
+        event_name_to_code(name)
+    }
+
+    /// Tests if two event names refer to the same event.
+    ///
+    /// Aliases and unit mask variants can resolve to the same event code.
+    /// Returns an error if either name is invalid.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     assert!(papi.events_equal("PAPI_TOT_INS", "PAPI_TOT_INS")?);
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn events_equal(&self, a: &str, b: &str) -> Result<bool> {
+        Ok(self.event_code(a)? == self.event_code(b)?)
+    }
+
+    /// Retrieves descriptive information about an event.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let info = papi.event_info_by_name("PAPI_TOT_CYC")?;
+    ///     println!("{} [{}]: {}", info.symbol, info.units, info.long_descr);
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn event_info_by_name(&self, name: &str) -> Result<EventInfo> {
+        let info = event_info(event_name_to_code(name)?)?;
+
+        Ok(EventInfo {
+            symbol: c_chars_to_string(&info.symbol),
+            short_descr: c_chars_to_string(&info.short_descr),
+            long_descr: c_chars_to_string(&info.long_descr),
+            units: c_chars_to_string(&info.units),
+            note: c_chars_to_string(&info.note),
+        })
+    }
+
+    /// Tests if an event is derived from multiple native events.
+    ///
+    /// The values of derived events are computed from the values of their
+    /// native events. Thus, derived events use up multiple hardware counters,
+    /// and their values are less accurate when multiplexing.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     if papi.is_derived("PAPI_L1_TCM")? {
+    ///         println!("PAPI_L1_TCM is derived");
+    ///     }
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn is_derived(&self, name: &str) -> Result<bool> {
+        let info = event_info(event_name_to_code(name)?)?;
+
+        Ok(is_derived(&info))
+    }
+
+    /// Lists the names of the native events that an event is composed of.
+    ///
+    /// Preset events list the native events that they map to, whereas native
+    /// events list themselves.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     for native in papi.derived_components("PAPI_L1_TCM")? {
+    ///         println!("{}", native);
+    ///     }
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn derived_components(&self, name: &str) -> Result<Vec<String>> {
+        let info = event_info(event_name_to_code(name)?)?;
+
+        Ok(info
+            .name
+            .iter()
+            .take(info.count as usize)
+            .map(|name| c_chars_to_string(name))
+            .collect())
+    }
+}
+
+impl Iterator for NativeEvents {
+    type Item = Result<NativeEventInfo>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let modifier = self.modifier?;
+
+        let info = enum_cmp_event(&mut self.code, modifier, self.component).and_then(|more| {
+            if !more {
+                return Ok(None);
+            }
+
+            let info = event_info(self.code)?;
+            Ok(Some(NativeEventInfo {
+                code: self.code,
+                symbol: c_chars_to_string(&info.symbol),
+                long_descr: c_chars_to_string(&info.long_descr),
+            }))
+        });
+
+        match info {
+            Ok(Some(_)) => self.modifier = Some(ffi::PAPI_ENUM_EVENTS as c_int),
+            Ok(None) | Err(_) => self.modifier = None,
+        }
+
+        info.transpose()
+    }
+}
+
+impl EventCode {
+    /// Resolves the event code of an event name.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     use papi::events::EventCode;
+    ///
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let code = EventCode::from_name(&papi, "PAPI_TOT_INS")?;
+    ///     assert_eq!(code.name()?, "PAPI_TOT_INS");
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn from_name(_papi: &Papi, name: &str) -> Result<Self> {
+        Ok(Self(event_name_to_code(name)?))
+    }
+
+    /// Wraps a code that is known to refer to an event.
+    pub(crate) fn from_code(code: i32) -> Self {
+        Self(code)
+    }
+
+    /// Resolves the name of the event.
+    pub fn name(&self) -> Result<String> {
+        Sample::event_code_to_name(self.0)
+    }
+
+    /// Looks up the index of the component that provides the event.
+    pub fn component(&self) -> Result<u32> {
+        let component = unsafe { ffi::PAPI_get_event_component(self.0) };
+        if component < 0 {
+            check(component)?;
+        }
+
+        Ok(component as u32)
+    }
+}
+
+impl TryFrom<i32> for EventCode {
+    type Error = Error;
+
+    /// Checks that the code refers to an event.
+    fn try_from(code: i32) -> Result<Self> {
+        Sample::event_code_to_name(code)?;
+        Ok(Self(code))
+    }
+}
+
+impl From<EventCode> for i32 {
+    fn from(code: EventCode) -> Self {
+        code.0
+    }
+}
+
+impl fmt::Display for EventCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Ok(name) => f.write_str(&name),
+            Err(_) => write!(f, "{:#x}", self.0),
+        }
+    }
+}
+
+/// Converts an event name to its PAPI event code.
+///
+/// Returns an `EventNotFound` error if PAPI doesn't know the event.
+pub(crate) fn event_name_to_code(name: &str) -> Result<i32> {
+    let c_name = CString::new(name).map_err(|_| ErrorKind::InvalidEvent("Invalid event name"))?;
+
+    let mut code: i32 = 0;
+    match unsafe { ffi::PAPI_event_name_to_code(c_name.as_ptr(), &mut code) } {
+        ffi::PAPI_ENOEVNT => Err(ErrorKind::EventNotFound(name.to_string()))?,
+        ret => check(ret)?,
+    }
+
+    Ok(code)
+}
+
+/// Retrieves the PAPI event info of an event code.
+pub(crate) fn event_info(code: i32) -> Result<ffi::PAPI_event_info_t> {
+    let mut info: ffi::PAPI_event_info_t = unsafe { mem::zeroed() };
+
+    unsafe {
+        check(ffi::PAPI_get_event_info(code, &mut info))?;
+    }
+
+    Ok(info)
+}
+
+/// Tests if the event info describes a derived event.
+fn is_derived(info: &ffi::PAPI_event_info_t) -> bool {
+    let derived = c_chars_to_string(&info.derived);
+    !derived.is_empty() && derived != NOT_DERIVED
+}
+
+/// Converts a nul-terminated C character array into an owned `String`.
+///
+/// Invalid UTF-8 sequences are replaced, and the array is truncated at the
+/// first nul character.
+pub(crate) fn c_chars_to_string(chars: &[c_char]) -> String {
+    let bytes: Vec<u8> = chars
+        .iter()
+        .take_while(|&&c| c != 0)
+        .map(|&c| c as u8)
+        .collect();
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Looks up the index of a component by its name.
+///
+/// Returns `None` if the component does not exist.
+pub(crate) fn component_index(name: &str) -> Result<Option<i32>> {
+    let c_name = CString::new(name)
+        .map_err(|_| ErrorKind::InvalidArgument(format!("Invalid component name {}", name)))?;
+
+    match unsafe { ffi::PAPI_get_component_index(c_name.as_ptr()) } {
+        ffi::PAPI_ENOCMP => Ok(None),
+        index if index < 0 => Err(ErrorKind::from_code(index).into()),
+        index => Ok(Some(index)),
+    }
+}
+
+/// Prefixes an event name with its component name, unless the event name is
+/// already qualified.
+fn qualify_event_name(component: &str, event: String) -> String {
+    if event.contains(":::") {
+        event
+    } else {
+        format!("{}:::{}", component, event)
+    }
+}
+
+/// Advances `code` to the next preset or native event.
+///
+/// Returns `false` if there are no more events to enumerate.
+fn enum_event(code: &mut c_int, modifier: c_int) -> Result<bool> {
+    let ret = unsafe { ffi::PAPI_enum_event(code, modifier) };
+    if ret == ffi::PAPI_ENOEVNT {
+        Ok(false)
+    } else {
+        check(ret)?;
+        Ok(true)
+    }
+}
+
+/// Advances `code` to the next event of the component.
+///
+/// Returns `false` if there are no more events to enumerate.
+fn enum_cmp_event(code: &mut c_int, modifier: c_int, component: i32) -> Result<bool> {
+    let ret = unsafe { ffi::PAPI_enum_cmp_event(code, modifier, component) };
+    if ret == ffi::PAPI_ENOEVNT {
+        Ok(false)
+    } else {
+        check(ret)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn enumerate_native_events() {
+        let papi = Papi::init().unwrap();
+        let events = papi.native_events(0, EnumModifier::All).unwrap();
+        assert!(!events.is_empty());
+    }
+
+    #[test]
+    fn enumerate_native_umasks() {
+        let papi = Papi::init().unwrap();
+        let events = papi.native_events(0, EnumModifier::All).unwrap();
+        let umasks = papi.native_events(0, EnumModifier::Umasks).unwrap();
+        assert!(umasks.len() >= events.len());
+    }
+
+    #[test]
+    fn iterate_native_events() {
+        let papi = Papi::init().unwrap();
+        let events = papi.native_events(0, EnumModifier::All).unwrap();
+        let symbols: Vec<String> = papi
+            .native_events_iter(0)
+            .map(|event| event.unwrap().symbol)
+            .collect();
+        assert_eq!(symbols.len(), events.len());
+
+        assert_eq!(papi.native_events_iter(0).take(1).count(), 1);
+    }
+
+    #[test]
+    fn list_available_presets() {
+        let papi = Papi::init().unwrap();
+        let presets = papi.available_presets().unwrap();
+        assert!(presets.iter().any(|p| p.symbol == "PAPI_TOT_INS"));
+        assert!(presets.iter().all(|p| p.symbol.starts_with("PAPI_")));
+    }
+
+    #[test]
+    fn list_components() {
+        let papi = Papi::init().unwrap();
+        let components = papi.components().unwrap();
+        assert!(!components.is_empty());
+        assert_eq!(components[0].index, 0);
+        assert!(components[0].name.starts_with("perf_event"));
+    }
+
+    #[test]
+    fn get_event_info() {
+        let papi = Papi::init().unwrap();
+        let info = papi.event_info_by_name("PAPI_TOT_CYC").unwrap();
+        assert_eq!(info.symbol, "PAPI_TOT_CYC");
+        assert!(!info.short_descr.is_empty());
+        assert!(!info.long_descr.is_empty());
+    }
+
+    #[test]
+    #[ignore]
+    fn query_derived_preset() {
+        // PAPI_L1_TCM is the sum of data and instruction cache misses on x86
+        let papi = Papi::init().unwrap();
+        assert!(papi.is_derived("PAPI_L1_TCM").unwrap());
+        assert!(papi.derived_components("PAPI_L1_TCM").unwrap().len() >= 2);
+
+        assert!(!papi.is_derived("PAPI_TOT_INS").unwrap());
+        assert_eq!(papi.derived_components("PAPI_TOT_INS").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn qualify_event_names() {
+        assert_eq!(
+            qualify_event_name("nvml", "nvml:::GPU:power".into()),
+            "nvml:::GPU:power"
+        );
+        assert_eq!(
+            qualify_event_name("nvml", "GPU:power".into()),
+            "nvml:::GPU:power"
+        );
+    }
+
+    #[test]
+    fn unknown_event_name() {
+        let _papi = Papi::init().unwrap();
+        match event_name_to_code("NOT_A_REAL_EVENT").unwrap_err().kind() {
+            ErrorKind::EventNotFound(name) => assert_eq!(name, "NOT_A_REAL_EVENT"),
+            other => panic!("Expected EventNotFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn convert_event_codes() {
+        let papi = Papi::init().unwrap();
+        let code = EventCode::from_name(&papi, "PAPI_TOT_INS").unwrap();
+        assert_eq!(code.name().unwrap(), "PAPI_TOT_INS");
+        assert_eq!(code.to_string(), "PAPI_TOT_INS");
+        assert_eq!(code.component().unwrap(), 0);
+
+        let raw: i32 = code.into();
+        assert_eq!(EventCode::try_from(raw).unwrap(), code);
+        assert!(EventCode::try_from(0).is_err());
+    }
+
+    #[test]
+    fn compare_event_names() {
+        let papi = Papi::init().unwrap();
+        assert!(papi.events_equal("PAPI_TOT_INS", "PAPI_TOT_INS").unwrap());
+        assert!(!papi.events_equal("PAPI_TOT_INS", "PAPI_TOT_CYC").unwrap());
+        assert!(papi
+            .events_equal("PAPI_TOT_INS", "NOT_A_REAL_EVENT")
+            .is_err());
+    }
+}
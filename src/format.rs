@@ -0,0 +1,126 @@
+// Copyright 2019 German Research Center for Artificial Intelligence (DFKI)
+// Author: Clemens Lutz <clemens.lutz@dfki.de>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Human-readable formatting of counter values.
+
+use super::error::Result;
+use super::event_set::Sample;
+use std::fmt::Write;
+
+/// The SI prefixes for powers of 1000, starting at 1000^1.
+const SI_PREFIXES: [&str; 6] = ["k", "M", "G", "T", "P", "E"];
+
+/// The smallest scaled value that rounds up to 1000 with two decimals, and
+/// thus requires the next prefix.
+const SI_ROUNDING_LIMIT: f64 = 999.995;
+
+/// Formats a count with an SI prefix, e.g., `1.23 G` for 1230000000.
+///
+/// Counts below 1000 are formatted without a prefix. Negative counts keep
+/// their sign.
+///
+///     use papi::format::si_count;
+///
+///     assert_eq!(si_count(0), "0");
+///     assert_eq!(si_count(1_230_000_000), "1.23 G");
+///     assert_eq!(si_count(-45_600), "-45.60 k");
+///
+pub fn si_count(value: i64) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    let mut scaled = (value as f64).abs();
+
+    if scaled < 1000.0 {
+        return value.to_string();
+    }
+
+    let mut prefix = SI_PREFIXES[0];
+    for &p in SI_PREFIXES.iter() {
+        scaled /= 1000.0;
+        prefix = p;
+        if scaled < SI_ROUNDING_LIMIT {
+            break;
+        }
+    }
+
+    format!("{}{:.2} {}", sign, scaled, prefix)
+}
+
+impl Sample {
+    /// Formats the sample as a table of event names and SI-prefixed values.
+    ///
+    /// Each event is written on its own line, with the values aligned.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::{EventSetBuilder, Sample};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     # let ready_event_set = EventSetBuilder::new(&papi)?
+    ///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+    ///     #     .build()?;
+    ///     # let mut sample = Sample::default();
+    ///     # ready_event_set.init_sample(&mut sample)?;
+    ///     # let running_event_set = ready_event_set.start()?;
+    ///     # running_event_set.stop(&mut sample)?;
+    ///     #
+    ///     println!("{}", sample.to_human_table()?);
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn to_human_table(&self) -> Result<String> {
+        let named_values = self.named_values()?;
+        let width = named_values
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+
+        let mut table = String::new();
+        for (name, value) in named_values {
+            writeln!(
+                table,
+                "{:<width$}  {:>10}",
+                name,
+                si_count(value),
+                width = width
+            )
+            .expect("Failed to write into string");
+        }
+
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn format_small_counts() {
+        assert_eq!(si_count(0), "0");
+        assert_eq!(si_count(999), "999");
+        assert_eq!(si_count(-999), "-999");
+    }
+
+    #[test]
+    fn format_prefixed_counts() {
+        assert_eq!(si_count(1000), "1.00 k");
+        assert_eq!(si_count(999_994), "999.99 k");
+        assert_eq!(si_count(999_999), "1.00 M");
+        assert_eq!(si_count(-999_999_999), "-1.00 G");
+        assert_eq!(si_count(999_995_000_000), "1.00 T");
+        assert_eq!(si_count(1_230_000_000), "1.23 G");
+        assert_eq!(si_count(-2_500_000), "-2.50 M");
+        assert_eq!(si_count(i64::MAX), "9.22 E");
+        assert_eq!(si_count(i64::MIN), "-9.22 E");
+    }
+}
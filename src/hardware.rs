@@ -0,0 +1,282 @@
+// Copyright 2019 German Research Center for Artificial Intelligence (DFKI)
+// Author: Clemens Lutz <clemens.lutz@dfki.de>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Information about the hardware that PAPI runs on.
+
+use super::error::{check, Result};
+use super::events::c_chars_to_string;
+use super::ffi;
+use super::Papi;
+
+/// The location of a logical CPU in the system topology.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CpuInfo {
+    /// The logical CPU number, as used by the operating system
+    pub cpu_id: u32,
+    /// The socket (i.e., package) that contains the CPU
+    pub socket: u32,
+    /// The physical core within the socket that contains the CPU
+    pub core: u32,
+    /// The NUMA node that contains the CPU
+    pub numa_node: u32,
+}
+
+/// A description of the hardware that PAPI runs on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HardwareInfo {
+    /// The CPU vendor, e.g., "GenuineIntel"
+    pub vendor: String,
+    /// The CPU model name
+    pub model: String,
+    /// The CPU clock frequency in MHz
+    pub mhz: f32,
+    /// The number of CPUs per NUMA node
+    pub ncpu: u32,
+    /// The number of sockets
+    pub sockets: u32,
+    /// The number of cores per socket
+    pub cores: u32,
+    /// The number of hardware threads per core
+    pub threads: u32,
+}
+
+impl Papi {
+    /// Retrieves a description of the CPU, e.g., for benchmark reports.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let info = papi.hardware_info()?;
+    ///     println!("{} {} with {} sockets", info.vendor, info.model, info.sockets);
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn hardware_info(&self) -> Result<HardwareInfo> {
+        let hw_info = hw_info()?;
+
+        Ok(HardwareInfo {
+            vendor: c_chars_to_string(&hw_info.vendor_string),
+            model: c_chars_to_string(&hw_info.model_string),
+            mhz: hw_info.mhz,
+            ncpu: hw_info.ncpu.max(0) as u32,
+            sockets: hw_info.sockets.max(0) as u32,
+            cores: hw_info.cores.max(0) as u32,
+            threads: hw_info.threads.max(0) as u32,
+        })
+    }
+
+    /// Retrieves the topology of all online CPUs.
+    ///
+    /// On Linux, the online CPUs and their topology are read from sysfs.
+    /// Otherwise, and if sysfs is unavailable, all CPUs are assumed to be
+    /// online and their topology is estimated from PAPI's hardware
+    /// information, assuming that CPUs are numbered consecutively by socket
+    /// and core.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     for cpu in papi.cpu_topology()? {
+    ///         println!("CPU {} is on socket {}", cpu.cpu_id, cpu.socket);
+    ///     }
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn cpu_topology(&self) -> Result<Vec<CpuInfo>> {
+        let hw_info = hw_info()?;
+        let threads = hw_info.threads.max(1) as u32;
+        let cores = hw_info.cores.max(1) as u32;
+        let nodes = hw_info.nnodes.max(1) as u32;
+
+        let estimate = |cpu_id: u32| {
+            let socket = cpu_id / (threads * cores);
+            CpuInfo {
+                cpu_id,
+                socket,
+                core: (cpu_id / threads) % cores,
+                numa_node: socket % nodes,
+            }
+        };
+
+        let cpus = match sysfs::online_cpus() {
+            Some(cpus) => cpus,
+            None => (0..hw_info.totalcpus.max(0) as u32).collect(),
+        };
+
+        Ok(cpus
+            .into_iter()
+            .map(|cpu_id| sysfs::cpu_info(cpu_id).unwrap_or_else(|| estimate(cpu_id)))
+            .collect())
+    }
+
+    /// Returns the number of hardware counters of the CPU component.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let num_counters = papi.num_hardware_counters()?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn num_hardware_counters(&self) -> Result<u32> {
+        self.num_hardware_counters_for_component(0)
+    }
+
+    /// Returns the number of hardware counters of a component.
+    ///
+    /// Components are enumerated by `Papi::components`.
+    pub fn num_hardware_counters_for_component(&self, component: i32) -> Result<u32> {
+        let num_counters = unsafe { ffi::PAPI_num_cmp_hwctrs(component) };
+        if num_counters < 0 {
+            check(num_counters)?;
+        }
+
+        Ok(num_counters as u32)
+    }
+}
+
+/// Retrieves PAPI's hardware information.
+pub(crate) fn hw_info() -> Result<&'static ffi::PAPI_hw_info_t> {
+    let hw_info = unsafe { ffi::PAPI_get_hardware_info() };
+    if hw_info.is_null() {
+        check(ffi::PAPI_ENOINIT)?;
+    }
+
+    Ok(unsafe { &*hw_info })
+}
+
+/// Parses a CPU list in the Linux sysfs format, e.g., `0-3,5,7-8`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_cpu_list(list: &str) -> Option<Vec<u32>> {
+    let mut cpus = Vec::new();
+
+    for range in list.trim().split(',').filter(|r| !r.is_empty()) {
+        let mut bounds = range.splitn(2, '-');
+        let first: u32 = bounds.next()?.parse().ok()?;
+        let last: u32 = match bounds.next() {
+            Some(l) => l.parse().ok()?,
+            None => first,
+        };
+        cpus.extend(first..=last);
+    }
+
+    Some(cpus)
+}
+
+#[cfg(target_os = "linux")]
+mod sysfs {
+    use super::{parse_cpu_list, CpuInfo};
+    use std::fs;
+    use std::path::Path;
+
+    const CPU_PATH: &str = "/sys/devices/system/cpu";
+
+    /// Reads the list of online CPUs.
+    pub(super) fn online_cpus() -> Option<Vec<u32>> {
+        let list = fs::read_to_string(Path::new(CPU_PATH).join("online")).ok()?;
+        parse_cpu_list(&list)
+    }
+
+    /// Reads the topology of a CPU.
+    pub(super) fn cpu_info(cpu_id: u32) -> Option<CpuInfo> {
+        let cpu_path = Path::new(CPU_PATH).join(format!("cpu{}", cpu_id));
+        let read_id = |file: &str| -> Option<u32> {
+            fs::read_to_string(cpu_path.join("topology").join(file))
+                .ok()?
+                .trim()
+                .parse()
+                .ok()
+        };
+
+        let socket = read_id("physical_package_id")?;
+        let core = read_id("core_id")?;
+
+        // The CPU directory contains a link to its NUMA node, e.g., "node0"
+        let numa_node = fs::read_dir(&cpu_path)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| name.strip_prefix("node"))
+                    .and_then(|node| node.parse().ok())
+            })
+            .next()
+            .unwrap_or(0);
+
+        Some(CpuInfo {
+            cpu_id,
+            socket,
+            core,
+            numa_node,
+        })
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sysfs {
+    use super::CpuInfo;
+
+    pub(super) fn online_cpus() -> Option<Vec<u32>> {
+        None
+    }
+
+    pub(super) fn cpu_info(_cpu_id: u32) -> Option<CpuInfo> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn parse_cpu_lists() {
+        assert_eq!(parse_cpu_list("0\n"), Some(vec![0]));
+        assert_eq!(
+            parse_cpu_list("0-3,5,7-8\n"),
+            Some(vec![0, 1, 2, 3, 5, 7, 8])
+        );
+        assert_eq!(parse_cpu_list("0-x"), None);
+    }
+
+    #[test]
+    fn count_cpu_hardware_counters() {
+        let papi = Papi::init().unwrap();
+        assert!(papi.num_hardware_counters().unwrap() >= 1);
+    }
+
+    #[test]
+    fn get_hardware_info() {
+        let papi = Papi::init().unwrap();
+        let info = papi.hardware_info().unwrap();
+        println!("{:?}", info);
+        assert!(!info.vendor.is_empty());
+    }
+
+    #[test]
+    fn get_cpu_topology() {
+        let papi = Papi::init().unwrap();
+        let cpus = papi.cpu_topology().unwrap();
+        assert!(!cpus.is_empty());
+    }
+}
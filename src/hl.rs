@@ -0,0 +1,155 @@
+// Copyright 2019 German Research Center for Artificial Intelligence (DFKI)
+// Author: Clemens Lutz <clemens.lutz@dfki.de>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Wrappers around the PAPI high-level region API.
+//!
+//! The high-level API was introduced with PAPI 6. It measures the events
+//! listed in the `PAPI_EVENTS` environment variable for each instrumented
+//! region, and writes the results as JSON files to the directory given by
+//! `PAPI_OUTPUT_DIRECTORY` when the program exits. Thus, the measurements
+//! integrate with PAPI's own tooling, e.g., `papi_hl_output_writer.py`.
+//!
+//! The high-level API initializes PAPI on its own, and manages its own event
+//! sets. Thus, it conflicts with low-level event sets that are running on the
+//! same thread.
+
+use super::error::{check, check_cleanup, ErrorKind, Result};
+use super::ffi;
+use std::ffi::CString;
+use std::marker::PhantomData;
+
+/// A measured region that ends when it is dropped.
+///
+///     # use std::error::Error;
+///     # use std::result::Result;
+///     use papi::hl::HlRegion;
+///
+///     # fn main() -> Result<(), Box<dyn Error>> {
+///     {
+///         let _region = HlRegion::begin("computation")?;
+///         let _sum: u64 = (0..1000).sum();
+///     }
+///     #
+///     # Ok(())
+///     # }
+///
+#[derive(Debug)]
+pub struct HlRegion {
+    name: CString,
+    phantom: PhantomData<*mut u8>, // unimplement Send and Sync
+}
+
+/// Begins measuring a region.
+///
+/// Regions are identified by their name, and can be nested.
+pub fn region_begin(name: &str) -> Result<()> {
+    let c_name = region_name(name)?;
+
+    unsafe {
+        check(ffi::PAPI_hl_region_begin(c_name.as_ptr()))?;
+    }
+
+    Ok(())
+}
+
+/// Ends measuring a region.
+pub fn region_end(name: &str) -> Result<()> {
+    let c_name = region_name(name)?;
+
+    unsafe {
+        check(ffi::PAPI_hl_region_end(c_name.as_ptr()))?;
+    }
+
+    Ok(())
+}
+
+/// Stops the high-level API, and writes the measurements of all regions.
+///
+/// By default, PAPI writes the measurements when the program exits. Stopping
+/// the high-level API writes them early, e.g., before a program hands over
+/// the counters to low-level event sets. Regions cannot be measured after
+/// stopping.
+///
+///     # use std::error::Error;
+///     # use std::result::Result;
+///     #
+///     # fn main() -> Result<(), Box<dyn Error>> {
+///     papi::hl::region_begin("computation")?;
+///     let _sum: u64 = (0..1000).sum();
+///     papi::hl::region_end("computation")?;
+///
+///     papi::hl::stop()?;
+///     #
+///     # Ok(())
+///     # }
+///
+pub fn stop() -> Result<()> {
+    unsafe {
+        check(ffi::PAPI_hl_stop())?;
+    }
+
+    Ok(())
+}
+
+impl HlRegion {
+    /// Begins measuring a region, and returns a guard that ends the region.
+    pub fn begin(name: &str) -> Result<Self> {
+        let name = region_name(name)?;
+
+        unsafe {
+            check(ffi::PAPI_hl_region_begin(name.as_ptr()))?;
+        }
+
+        Ok(Self {
+            name,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl Drop for HlRegion {
+    fn drop(&mut self) {
+        unsafe {
+            check_cleanup(
+                ffi::PAPI_hl_region_end(self.name.as_ptr()),
+                "Failed to end PAPI high-level region",
+            );
+        }
+    }
+}
+
+/// Converts a region name into a C string.
+fn region_name(name: &str) -> Result<CString> {
+    CString::new(name)
+        .map_err(|_| ErrorKind::InvalidArgument(format!("Invalid region name {}", name)).into())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn invalid_region_name() {
+        assert!(region_name("region\0").is_err());
+        assert!(region_name("region").is_ok());
+    }
+
+    #[test]
+    #[ignore]
+    fn measure_region() {
+        let region = HlRegion::begin("measure_region").unwrap();
+        let _sum: u64 = std::hint::black_box(0..1000).sum();
+        drop(region);
+
+        region_begin("measure_region_fn").unwrap();
+        region_end("measure_region_fn").unwrap();
+
+        stop().unwrap();
+    }
+}
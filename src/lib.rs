@@ -8,29 +8,135 @@
 
 pub mod error;
 pub mod event_set;
+pub mod events;
+pub mod format;
+pub mod hardware;
+pub mod hl;
+pub mod multi_component;
+mod overflow;
+pub mod permissions;
+pub mod presets;
+pub mod process;
+pub mod rapl;
+pub mod rates;
+pub mod report;
+pub mod timing;
+pub mod uncore;
 
 #[cfg(feature = "criterion")]
 pub mod criterion;
 
-use crate::error::Result;
+pub use crate::error::{set_cleanup_error_hook, ECNFLCT, ENOEVNT, ENOMEM};
+pub use crate::event_set::{measure, try_measure};
+
+use crate::error::{check, check_cleanup, ErrorKind, Result};
+use crate::event_set::{EventSetBuilder, ReadyEventSet, Sample};
 
 use papi_sys as ffi;
 
 use error_chain::bail;
 use serde_derive::Deserialize;
 use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::ffi::CStr;
 use std::fs;
 use std::io::Read;
+use std::marker::PhantomData;
+use std::panic::AssertUnwindSafe;
 use std::path;
+use std::sync::{Mutex, MutexGuard};
+
+/// Initializes PAPI and builds an event set in one step.
+///
+/// Returns the `Papi` instance together with the event set. The event set
+/// keeps the PAPI library initialized on its own, as do the samples that it
+/// initializes. Thus, the `Papi` instance may be dropped before the event
+/// set. Keep it only to build further event sets or to query PAPI, e.g., for
+/// the available events.
+///
+///     # use std::error::Error;
+///     # use std::result::Result;
+///     # use papi::event_set::Sample;
+///     #
+///     # fn main() -> Result<(), Box<dyn Error>> {
+///     let (_papi, ready_event_set) = papi::quick_set(&["PAPI_TOT_INS"])?;
+///
+///     let mut sample = Sample::default();
+///     ready_event_set.init_sample(&mut sample)?;
+///     let running_event_set = ready_event_set.start()?;
+///     running_event_set.stop(&mut sample)?;
+///     #
+///     # Ok(())
+///     # }
+///
+pub fn quick_set(events: &[&str]) -> Result<(Papi, ReadyEventSet)> {
+    let papi = Papi::init()?;
+    let ready_event_set = EventSetBuilder::new(&papi)?
+        .add_events_by_name(events)?
+        .build()?;
+
+    Ok((papi, ready_event_set))
+}
 
+/// The number of live references to the PAPI library.
+static LIBRARY_REFS: Mutex<usize> = Mutex::new(0);
+
+/// PAPI library handle
+///
+/// Multiple handles can coexist. PAPI is shut down when the last handle, the
+/// last event set, and the last sample initialized by an event set are
+/// dropped, and initialized again by the next call to `init`.
 #[derive(Debug)]
 pub struct Papi {
     config: Option<Config>,
+    library: LibraryRef,
+}
+
+/// A reference that keeps the PAPI library initialized.
+///
+/// Held by `Papi` handles and event sets, as event sets don't borrow the
+/// `Papi` handle that created them.
+#[derive(Debug)]
+pub(crate) struct LibraryRef(());
+
+/// A guard that unregisters the current thread from PAPI when it is dropped.
+///
+/// See `Papi::register_thread`.
+#[derive(Debug)]
+pub struct ThreadGuard {
+    _library: LibraryRef,          // keeps PAPI initialized until unregistering
+    phantom: PhantomData<*mut u8>, // unimplement Send and Sync
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
-    presets: Option<BTreeMap<String, Vec<String>>>,
+    presets: Option<BTreeMap<String, Preset>>,
+}
+
+/// The format of a configuration file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConfigFormat {
+    Toml,
+    /// Requires the `json` feature
+    Json,
+}
+
+/// A preset of the configuration file.
+///
+/// A preset is either a plain list of events, or a table with the list of
+/// events, an optional description, and an optional component name. Presets
+/// without a component use the CPU component.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Preset {
+    Events(Vec<String>),
+    Described {
+        events: Vec<String>,
+        #[serde(default)]
+        description: Option<String>,
+        #[serde(default)]
+        component: Option<String>,
+    },
 }
 
 /// PAPI library wrapper
@@ -42,6 +148,8 @@ impl Papi {
     ///     assert!(Papi::init().is_ok());
     ///
     pub fn init() -> Result<Self> {
+        let mut refs = LibraryRef::lock();
+
         if unsafe { ffi::PAPI_is_initialized() } != ffi::PAPI_LOW_LEVEL_INITED as i32 {
             if unsafe { ffi::PAPI_library_init(ffi::PAPI_VER_CURRENT) != ffi::PAPI_VER_CURRENT } {
                 bail!("PAPI library version mismatch!");
@@ -52,7 +160,12 @@ impl Papi {
             bail!("Unable to initialize PAPI threads");
         }
 
-        Ok(Papi { config: None })
+        *refs += 1;
+
+        Ok(Papi {
+            config: None,
+            library: LibraryRef(()),
+        })
     }
 
     pub fn init_with_config(config: Config) -> Result<Self> {
@@ -60,10 +173,155 @@ impl Papi {
         papi.config = Some(config);
         Ok(papi)
     }
+
+    /// Registers the current thread with PAPI.
+    ///
+    /// PAPI tracks counters per thread. Threads that are not spawned by the
+    /// thread that initialized PAPI, e.g., the worker threads of a thread
+    /// pool such as rayon's, must be registered before they start an event
+    /// set. Call `register_thread` at the top of each worker closure, and
+    /// keep the guard alive while measuring. The thread is unregistered when
+    /// the guard is dropped.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     # use papi::event_set::EventSetBuilder;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     let papi = Papi::init()?;
+    ///
+    ///     std::thread::scope(|s| {
+    ///         s.spawn(|| -> papi::error::Result<()> {
+    ///             let _guard = papi.register_thread()?;
+    ///             let ready_event_set = EventSetBuilder::new(&papi)?
+    ///                 .add_event_by_name("PAPI_TOT_INS")?
+    ///                 .build()?;
+    ///             let (_, sample) = ready_event_set.measure(|| (0..100).sum::<u32>())?;
+    ///             Ok(())
+    ///         })
+    ///         .join()
+    ///         .unwrap()
+    ///     })?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn register_thread(&self) -> Result<ThreadGuard> {
+        ThreadGuard::register(self.library())
+    }
+
+    /// Sets the sampling period of PAPI's interval timer in nanoseconds.
+    ///
+    /// The interval timer drives time-based sampling, such as the time slices
+    /// of multiplexed event sets. A shorter period samples at a finer
+    /// granularity, at the cost of a higher overhead.
+    ///
+    /// The option is supported by the PAPI 5 and 6 series. Returns an error if
+    /// the period is zero or exceeds the range of PAPI's timer, or if the
+    /// installed PAPI version rejects the option.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     let papi = Papi::init()?;
+    ///     papi.set_sampling_period(1_000_000)?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn set_sampling_period(&self, nanos: u64) -> Result<()> {
+        let ns = match i32::try_from(nanos) {
+            Ok(ns) if ns > 0 => ns,
+            _ => Err(ErrorKind::InvalidArgument(format!(
+                "Sampling period of {} ns is out of range",
+                nanos
+            )))?,
+        };
+
+        unsafe {
+            let mut option: ffi::PAPI_option_t = std::mem::zeroed();
+            option.itimer.ns = ns;
+            check(ffi::PAPI_set_opt(
+                ffi::PAPI_DEF_ITIMER_NS as i32,
+                &mut option,
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Measures a workload once per preset, and returns the samples keyed by
+    /// preset name.
+    ///
+    /// Each preset is measured in a separate pass, i.e., the workload runs once
+    /// for each preset. The samples are thus only comparable if the workload is
+    /// deterministic, and performs the same work in each pass. Presets are
+    /// looked up in the configuration as by `EventSetBuilder::use_preset`.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use std::path::Path;
+    ///     # use papi::{Config, Papi};
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let config = Config::parse_file(Path::new("resources/configuration.toml"))?;
+    ///     let papi = Papi::init_with_config(config)?;
+    ///     let samples = papi.profile_presets(&["Test1", "Test2"], || {
+    ///         (0..100).sum::<u32>();
+    ///     })?;
+    ///
+    ///     for (preset, sample) in &samples {
+    ///         println!("{}:\n{}", preset, sample);
+    ///     }
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn profile_presets<F: Fn()>(
+        &self,
+        presets: &[&str],
+        workload: F,
+    ) -> Result<BTreeMap<String, Sample>> {
+        let mut samples = BTreeMap::new();
+
+        for &preset in presets {
+            let ready_event_set = EventSetBuilder::new(self)?
+                .with_label(preset)
+                .use_preset(preset)?
+                .build()?;
+            let ((), sample) = ready_event_set.measure(AssertUnwindSafe(&workload))?;
+            samples.insert(preset.to_string(), sample);
+        }
+
+        Ok(samples)
+    }
+
+    /// Converts a PAPI return code into an error message
+    ///
+    ///     # use papi::Papi;
+    ///     let message = Papi::strerror(-1);
+    ///     assert!(!message.is_empty());
+    ///
+    pub fn strerror(code: i32) -> String {
+        let str_ptr = unsafe { ffi::PAPI_strerror(code) };
+        if str_ptr.is_null() {
+            return format!("Unknown PAPI error code {}", code);
+        }
+
+        unsafe { CStr::from_ptr(str_ptr) }
+            .to_string_lossy()
+            .into_owned()
+    }
 }
 
 impl Config {
-    /// Load configuration file in TOML format
+    /// Load configuration file
+    ///
+    /// The format is determined by the file extension, i.e., files with a
+    /// `.json` extension are parsed as JSON, and all other files as TOML.
     ///
     ///     # use std::error::Error;
     ///     # use std::result::Result;
@@ -83,7 +341,113 @@ impl Config {
 
         fs::File::open(config).and_then(|mut f| f.read_to_string(&mut input))?;
 
-        Self::parse_str(&input)
+        Self::parse_str_as(&input, ConfigFormat::from_path(config))
+    }
+
+    /// Load configuration from a string in the given format
+    pub fn parse_str_as(config: &str, format: ConfigFormat) -> Result<Self> {
+        match format {
+            ConfigFormat::Toml => Self::parse_str(config),
+            #[cfg(feature = "json")]
+            ConfigFormat::Json => Self::parse_json_str(config),
+            #[cfg(not(feature = "json"))]
+            ConfigFormat::Json => Err(ErrorKind::InvalidArgument(
+                "Parsing JSON configurations requires the json feature".into(),
+            ))?,
+        }
+    }
+
+    /// Load configuration file in JSON format
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use std::path::Path;
+    ///     use papi::Config;
+    ///
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     let path = Path::new("resources/configuration.json");
+    ///     let config = Config::parse_json_file(path)?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    #[cfg(feature = "json")]
+    pub fn parse_json_file(config: &path::Path) -> Result<Self> {
+        let mut input = String::new();
+
+        fs::File::open(config).and_then(|mut f| f.read_to_string(&mut input))?;
+
+        Self::parse_json_str(&input)
+    }
+
+    /// Load configuration from a string in JSON format
+    ///
+    /// The JSON configuration has the same structure as the TOML
+    /// configuration.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     use papi::Config;
+    ///
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     let config_str = r#"{
+    ///         "presets": {
+    ///             "Test1": ["UOPS_RETIRED:ALL", "UOPS_RETIRED:STALL_CYCLES"],
+    ///             "Test3": ["UOPS_EXECUTED:THREAD"]
+    ///         }
+    ///     }"#;
+    ///
+    ///     let config = Config::parse_json_str(&config_str)?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    #[cfg(feature = "json")]
+    pub fn parse_json_str(config: &str) -> Result<Self> {
+        let deserialized: Self = serde_json::from_str(config)?;
+
+        Ok(deserialized)
+    }
+
+    /// Load and merge multiple configuration files
+    ///
+    /// The format is determined per file by its extension (see
+    /// `parse_file`), so TOML and JSON files can be mixed. The files are
+    /// merged in order, i.e., presets of later files override presets with
+    /// the same name of earlier files (see `merge`).
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use std::path::Path;
+    ///     use papi::Config;
+    ///
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     let shared = Path::new("resources/configuration.toml");
+    ///     # let machine = shared;
+    ///     let config = Config::parse_files(&[shared, machine])?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn parse_files(configs: &[&path::Path]) -> Result<Self> {
+        configs
+            .iter()
+            .try_fold(Config { presets: None }, |mut merged, &config| {
+                merged.merge(Self::parse_file(config)?);
+                Ok(merged)
+            })
+    }
+
+    /// Merges another configuration into this configuration
+    ///
+    /// Presets of the other configuration are added, and replace presets with
+    /// the same name.
+    pub fn merge(&mut self, other: Config) {
+        if let Some(presets) = other.presets {
+            self.presets
+                .get_or_insert_with(BTreeMap::new)
+                .extend(presets);
+        }
     }
 
     /// Load configuration from a string in TOML format
@@ -110,4 +474,347 @@ impl Config {
 
         Ok(deserialized)
     }
+
+    /// Returns the description of a preset.
+    ///
+    /// Returns `None` if the preset doesn't exist or has no description.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     use papi::Config;
+    ///
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     let config_str = r#"
+    ///     [presets]
+    ///     Test1 = ["UOPS_RETIRED:ALL", "UOPS_RETIRED:STALL_CYCLES"]
+    ///
+    ///     [presets.Test2]
+    ///     events = ["UOPS_EXECUTED:CORE", "UOPS_EXECUTED:STALL_CYCLES"]
+    ///     description = "Executed and stalled uops per core"
+    ///     "#;
+    ///
+    ///     let config = Config::parse_str(&config_str)?;
+    ///     assert_eq!(config.preset_description("Test1"), None);
+    ///     assert_eq!(
+    ///         config.preset_description("Test2"),
+    ///         Some("Executed and stalled uops per core")
+    ///     );
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn preset_description(&self, name: &str) -> Option<&str> {
+        match self.presets.as_ref()?.get(name)? {
+            Preset::Events(_) => None,
+            Preset::Described { description, .. } => description.as_deref(),
+        }
+    }
+}
+
+impl Papi {
+    /// Returns a reference that keeps the PAPI library initialized.
+    pub(crate) fn library(&self) -> LibraryRef {
+        self.library.clone()
+    }
+}
+
+impl LibraryRef {
+    /// Locks the reference count.
+    fn lock() -> MutexGuard<'static, usize> {
+        LIBRARY_REFS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns the number of live references.
+    #[cfg(test)]
+    fn count() -> usize {
+        *Self::lock()
+    }
+}
+
+impl Clone for LibraryRef {
+    fn clone(&self) -> Self {
+        *Self::lock() += 1;
+        LibraryRef(())
+    }
+}
+
+impl Drop for LibraryRef {
+    fn drop(&mut self) {
+        let mut refs = Self::lock();
+        *refs -= 1;
+        if *refs == 0 {
+            unsafe { ffi::PAPI_shutdown() };
+        }
+    }
+}
+
+impl ThreadGuard {
+    /// Registers the current thread with PAPI.
+    pub(crate) fn register(library: LibraryRef) -> Result<Self> {
+        unsafe {
+            check(ffi::PAPI_register_thread())?;
+        }
+
+        Ok(ThreadGuard {
+            _library: library,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl Drop for ThreadGuard {
+    fn drop(&mut self) {
+        unsafe {
+            check_cleanup(
+                ffi::PAPI_unregister_thread(),
+                "Failed to unregister thread from PAPI",
+            );
+        }
+    }
+}
+
+impl ConfigFormat {
+    /// Determines the format of a configuration file by its extension
+    pub fn from_path(path: &path::Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Toml,
+        }
+    }
+}
+
+impl Preset {
+    /// Returns the events of the preset.
+    pub(crate) fn events(&self) -> &[String] {
+        match self {
+            Preset::Events(events) => events,
+            Preset::Described { events, .. } => events,
+        }
+    }
+
+    /// Returns the component name of the preset, if any.
+    pub(crate) fn component(&self) -> Option<&str> {
+        match self {
+            Preset::Events(_) => None,
+            Preset::Described { component, .. } => component.as_deref(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn parse_described_presets() {
+        let config = Config::parse_str(
+            r#"
+            [presets]
+            Plain = ["PAPI_TOT_INS"]
+
+            [presets.Described]
+            events = ["PAPI_TOT_INS", "PAPI_TOT_CYC"]
+            description = "Instructions and cycles"
+            "#,
+        )
+        .unwrap();
+
+        let presets = config.presets.as_ref().unwrap();
+        assert_eq!(presets["Plain"].events(), ["PAPI_TOT_INS"]);
+        assert_eq!(
+            presets["Described"].events(),
+            ["PAPI_TOT_INS", "PAPI_TOT_CYC"]
+        );
+
+        assert_eq!(config.preset_description("Plain"), None);
+        assert_eq!(
+            config.preset_description("Described"),
+            Some("Instructions and cycles")
+        );
+        assert_eq!(config.preset_description("Missing"), None);
+    }
+
+    #[test]
+    fn parse_preset_components() {
+        let config = Config::parse_str(
+            r#"
+            [presets]
+            Plain = ["PAPI_TOT_INS"]
+
+            [presets.Energy]
+            component = "rapl"
+            events = ["rapl:::PACKAGE_ENERGY:PACKAGE0"]
+            "#,
+        )
+        .unwrap();
+
+        let presets = config.presets.as_ref().unwrap();
+        assert_eq!(presets["Plain"].component(), None);
+        assert_eq!(presets["Energy"].component(), Some("rapl"));
+        assert_eq!(
+            presets["Energy"].events(),
+            ["rapl:::PACKAGE_ENERGY:PACKAGE0"]
+        );
+        assert_eq!(config.preset_description("Energy"), None);
+    }
+
+    #[test]
+    fn measure_on_registered_thread() {
+        let papi = Papi::init().unwrap();
+
+        let sample = std::thread::scope(|s| {
+            s.spawn(|| {
+                let _guard = papi.register_thread().unwrap();
+                let ready_event_set = EventSetBuilder::new(&papi)
+                    .unwrap()
+                    .add_event_by_name("PAPI_TOT_INS")
+                    .unwrap()
+                    .build()
+                    .unwrap();
+                let (_, sample) = ready_event_set
+                    .measure(|| std::hint::black_box(0..1000_u64).sum::<u64>())
+                    .unwrap();
+                sample
+            })
+            .join()
+            .unwrap()
+        });
+
+        assert!(sample.total() > 0);
+    }
+
+    // Requires that no other test holds a reference, i.e., --test-threads=1
+    #[test]
+    #[ignore]
+    fn reinit_after_drop() {
+        let papi = Papi::init().unwrap();
+        assert_eq!(LibraryRef::count(), 1);
+        drop(papi);
+        assert_eq!(LibraryRef::count(), 0);
+        assert_eq!(unsafe { ffi::PAPI_is_initialized() }, 0);
+
+        let papi = Papi::init().unwrap();
+        assert_eq!(LibraryRef::count(), 1);
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        // The event set keeps PAPI initialized
+        drop(papi);
+        assert_eq!(LibraryRef::count(), 1);
+        let ((), sample) = ready_event_set.measure(|| ()).unwrap();
+        assert!(sample.total() >= 0);
+
+        // Measuring consumed the event set, but the sample resolves event names
+        assert_eq!(LibraryRef::count(), 1);
+        drop(sample);
+        assert_eq!(LibraryRef::count(), 0);
+    }
+
+    #[test]
+    fn merge_presets() {
+        let mut config = Config::parse_str(
+            r#"
+            [presets]
+            Shared = ["PAPI_TOT_INS"]
+            Overridden = ["PAPI_TOT_INS"]
+            "#,
+        )
+        .unwrap();
+        let machine = Config::parse_str(
+            r#"
+            [presets]
+            Overridden = ["PAPI_TOT_CYC"]
+            Added = ["PAPI_REF_CYC"]
+            "#,
+        )
+        .unwrap();
+
+        config.merge(machine);
+
+        let presets = config.presets.as_ref().unwrap();
+        assert_eq!(presets.len(), 3);
+        assert_eq!(presets["Shared"].events(), ["PAPI_TOT_INS"]);
+        assert_eq!(presets["Overridden"].events(), ["PAPI_TOT_CYC"]);
+        assert_eq!(presets["Added"].events(), ["PAPI_REF_CYC"]);
+    }
+
+    #[test]
+    fn merge_into_empty_config() {
+        let mut config = Config::parse_str("").unwrap();
+        config.merge(Config::parse_str("[presets]\nAdded = [\"PAPI_TOT_INS\"]").unwrap());
+
+        let presets = config.presets.as_ref().unwrap();
+        assert_eq!(presets["Added"].events(), ["PAPI_TOT_INS"]);
+    }
+
+    #[test]
+    fn detect_config_format() {
+        assert_eq!(
+            ConfigFormat::from_path(path::Path::new("presets.json")),
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::from_path(path::Path::new("presets.toml")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(path::Path::new("presets")),
+            ConfigFormat::Toml
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn parse_json_config() {
+        let toml = Config::parse_file(path::Path::new("resources/configuration.toml")).unwrap();
+        let json = Config::parse_file(path::Path::new("resources/configuration.json")).unwrap();
+
+        let toml_presets = toml.presets.as_ref().unwrap();
+        let json_presets = json.presets.as_ref().unwrap();
+        assert_eq!(
+            toml_presets.keys().collect::<Vec<_>>(),
+            json_presets.keys().collect::<Vec<_>>()
+        );
+        for (name, preset) in toml_presets {
+            assert_eq!(preset.events(), json_presets[name].events());
+        }
+    }
+
+    #[test]
+    fn parse_multiple_files() {
+        let path = path::Path::new("resources/configuration.toml");
+        let config = Config::parse_files(&[path, path]).unwrap();
+        assert_eq!(config.presets.as_ref().unwrap().len(), 3);
+    }
+
+    #[test]
+    #[ignore]
+    fn profile_presets() {
+        let config = Config::parse_str(
+            r#"
+            [presets]
+            Instructions = ["PAPI_TOT_INS"]
+            Cycles = ["PAPI_TOT_CYC"]
+            "#,
+        )
+        .unwrap();
+        let papi = Papi::init_with_config(config).unwrap();
+
+        let samples = papi
+            .profile_presets(&["Instructions", "Cycles"], || {
+                let _sum: u64 = std::hint::black_box(0..1_000_000).sum();
+            })
+            .unwrap();
+
+        assert_eq!(samples.len(), 2);
+        assert!(samples["Instructions"].total() > 0);
+        assert!(samples["Cycles"].total() > 0);
+    }
 }
@@ -0,0 +1,195 @@
+// Copyright 2019 German Research Center for Artificial Intelligence (DFKI)
+// Author: Clemens Lutz <clemens.lutz@dfki.de>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Simultaneous measurement with event sets of multiple components.
+//!
+//! PAPI requires a separate event set for each component, e.g., one for the
+//! CPU and one for RAPL. A `MultiComponentSet` manages the lifecycles of
+//! these event sets together, such that they measure the same time span.
+//!
+//! # Skew
+//!
+//! PAPI cannot start or stop multiple event sets atomically. Instead, the
+//! event sets are started one after another, and stopped in the same order.
+//! Thus, each event set measures a span of approximately equal length, but
+//! the spans are shifted by the time taken to start the preceding event sets.
+//!
+//! To keep this skew small, all samples are initialized before starting, so
+//! that `start_all` and `stop_all` only perform the PAPI start and stop
+//! calls.
+
+use super::error::Result;
+use super::event_set::{ReadyEventSet, RunningEventSet, Sample};
+
+/// A group of event sets that are ready to measure simultaneously.
+#[derive(Debug)]
+pub struct MultiComponentSet {
+    event_sets: Vec<ReadyEventSet>,
+    samples: Vec<Sample>,
+}
+
+/// A group of event sets that are measuring simultaneously.
+#[derive(Debug)]
+pub struct RunningMultiComponentSet {
+    event_sets: Vec<RunningEventSet>,
+    samples: Vec<Sample>,
+}
+
+impl MultiComponentSet {
+    /// Groups event sets, typically of different components, for
+    /// simultaneous measurement.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     use papi::event_set::EventSetBuilder;
+    ///     use papi::multi_component::MultiComponentSet;
+    ///
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let cpu = EventSetBuilder::new(&papi)?
+    ///         .add_event_by_name("PAPI_TOT_INS")?
+    ///         .build()?;
+    ///     let rapl = EventSetBuilder::new(&papi)?
+    ///         .add_event_by_name("rapl:::PACKAGE_ENERGY:PACKAGE0")?
+    ///         .build()?;
+    ///
+    ///     let multi_set = MultiComponentSet::new(vec![cpu, rapl])?;
+    ///     let running = multi_set.start_all()?;
+    ///     let samples = running.stop_all()?;
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn new(event_sets: Vec<ReadyEventSet>) -> Result<Self> {
+        let samples = event_sets
+            .iter()
+            .map(|event_set| {
+                let mut sample = Sample::default();
+                event_set.init_sample(&mut sample)?;
+                Ok(sample)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            event_sets,
+            samples,
+        })
+    }
+
+    /// Returns the number of event sets in the group.
+    pub fn len(&self) -> usize {
+        self.event_sets.len()
+    }
+
+    /// Returns `true` if the group contains no event sets.
+    pub fn is_empty(&self) -> bool {
+        self.event_sets.is_empty()
+    }
+
+    /// Starts all event sets in sequence.
+    ///
+    /// If an event set fails to start, the event sets started so far are
+    /// stopped and destroyed, as are the event sets not yet started. Then,
+    /// the error is returned.
+    pub fn start_all(self) -> Result<RunningMultiComponentSet> {
+        let mut event_sets = Vec::with_capacity(self.event_sets.len());
+        let mut ready_event_sets = self.event_sets.into_iter();
+
+        while let Some(event_set) = ready_event_sets.next() {
+            match event_set.start() {
+                Ok(running) => event_sets.push(running),
+                Err(e) => {
+                    // Errors are ignored in favor of the start error
+                    for running in event_sets {
+                        let _ = running.destroy();
+                    }
+                    for ready in ready_event_sets {
+                        let _ = ready.destroy();
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(RunningMultiComponentSet {
+            event_sets,
+            samples: self.samples,
+        })
+    }
+}
+
+impl RunningMultiComponentSet {
+    /// Stops all event sets in the order they were started.
+    ///
+    /// Returns one sample per event set, in the order in which the event sets
+    /// were given to `MultiComponentSet::new`.
+    ///
+    /// All event sets are stopped, even if stopping one of them fails. An
+    /// event set that fails to stop is destroyed. Then, the first error is
+    /// returned, and the samples are discarded.
+    pub fn stop_all(mut self) -> Result<Vec<Sample>> {
+        let mut first_error = None;
+
+        for (event_set, sample) in self.event_sets.into_iter().zip(self.samples.iter_mut()) {
+            if let Err(e) = event_set.stop_counters(sample) {
+                // Errors are ignored in favor of the stop error
+                let _ = event_set.destroy();
+                first_error.get_or_insert(e);
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(self.samples),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::event_set::EventSetBuilder;
+    use crate::rapl;
+    use crate::Papi;
+
+    #[test]
+    #[ignore]
+    fn measure_cpu_and_rapl() {
+        let papi = Papi::init().unwrap();
+        let package = rapl::packages(&papi)
+            .unwrap()
+            .into_iter()
+            .next()
+            .expect("RAPL is not available");
+
+        let cpu = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+        let rapl = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name(&package.events[0])
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let multi_set = MultiComponentSet::new(vec![cpu, rapl]).unwrap();
+        assert_eq!(multi_set.len(), 2);
+
+        let running = multi_set.start_all().unwrap();
+        let _sum: u64 = (0..1_000_000).sum();
+        let samples = running.stop_all().unwrap();
+
+        assert_eq!(samples.len(), 2);
+        assert!(samples[0].total() > 0);
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright 2019 German Research Center for Artificial Intelligence (DFKI)
+// Author: Clemens Lutz <clemens.lutz@dfki.de>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Dispatch of PAPI overflow notifications to Rust closures.
+//!
+//! PAPI notifies overflows with a C callback, which runs inside a signal
+//! handler on the thread that runs the event set. The callback looks up the
+//! Rust closures of the event set in a thread-local registry. As event sets
+//! are bound to their thread, the registry doesn't require locking.
+//!
+//! A `ReadyEventSet` can be moved to another thread. Its handlers then remain
+//! in the registry of the thread that registered them, and can only be
+//! removed on that thread. Event sets thus refuse to rebind while they have
+//! handlers on another thread. If such an event set is destroyed on another
+//! thread, its stale handlers are discarded when PAPI reuses the identifier
+//! of the event set and handlers are registered for the new event set.
+//!
+//! Overflows that occur while the registry is being modified are dropped,
+//! because the closures cannot be accessed safely at that time.
+
+use super::error::{check, ErrorKind, Result, ResultExt};
+use super::ffi;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::os::raw::{c_int, c_longlong, c_void};
+use std::thread::{self, ThreadId};
+
+/// The maximum number of events that can overflow at once, i.e., the number
+/// of bits in the overflow vector.
+const MAX_OVERFLOW_EVENTS: usize = 64;
+
+/// A closure that handles the overflows of an event.
+type OverflowHandler = Box<dyn FnMut(i32, usize)>;
+
+/// The overflow handlers of an event set.
+struct EventSetHandlers {
+    /// The event codes of the event set, in the order of their indices
+    event_codes: Vec<i32>,
+    /// The handlers, keyed by their event codes
+    handlers: HashMap<i32, OverflowHandler>,
+}
+
+thread_local! {
+    static HANDLERS: RefCell<HashMap<c_int, EventSetHandlers>> = RefCell::new(HashMap::new());
+}
+
+/// Registers an overflow handler for an event of an event set.
+///
+/// `event_codes` lists the events of the event set. `first` is set for the
+/// first handler of the event set, which discards any stale handlers that
+/// are registered with the same identifier.
+pub(crate) fn register(
+    event_set: c_int,
+    event_codes: Vec<i32>,
+    event_code: i32,
+    event_name: &str,
+    threshold: i32,
+    handler: OverflowHandler,
+    first: bool,
+) -> Result<()> {
+    if threshold <= 0 {
+        Err(ErrorKind::InvalidArgument(format!(
+            "Overflow threshold must be positive, but is {}",
+            threshold
+        )))?;
+    }
+
+    HANDLERS.with(|handlers| {
+        let mut handlers = handlers.borrow_mut();
+        let event_set_handlers = handlers
+            .entry(event_set)
+            .or_insert_with(|| EventSetHandlers {
+                event_codes: Vec::new(),
+                handlers: HashMap::new(),
+            });
+        if first {
+            event_set_handlers.handlers.clear();
+        }
+        event_set_handlers.event_codes = event_codes;
+    });
+
+    unsafe {
+        check(ffi::PAPI_overflow(
+            event_set,
+            event_code,
+            threshold,
+            0,
+            Some(dispatch),
+        ))
+    }
+    .chain_err(|| {
+        format!(
+            "Event {} cannot overflow; PAPI supports overflows only on non-derived events",
+            event_name
+        )
+    })?;
+
+    HANDLERS.with(|handlers| {
+        if let Some(event_set_handlers) = handlers.borrow_mut().get_mut(&event_set) {
+            event_set_handlers.handlers.insert(event_code, handler);
+        }
+    });
+
+    Ok(())
+}
+
+/// Removes all overflow handlers of an event set.
+///
+/// `owner` is the thread that registered the handlers, if any. Handlers of
+/// another thread cannot be removed, and are discarded by `register` instead.
+///
+/// Must be called when the event set is destroyed, as PAPI reuses the
+/// identifiers of destroyed event sets.
+pub(crate) fn unregister(event_set: c_int, owner: Option<ThreadId>) {
+    if owner != Some(thread::current().id()) {
+        return;
+    }
+
+    HANDLERS.with(|handlers| {
+        if let Ok(mut handlers) = handlers.try_borrow_mut() {
+            handlers.remove(&event_set);
+        }
+    });
+}
+
+/// Dispatches an overflow notification to the handlers of the overflowing
+/// events.
+unsafe extern "C" fn dispatch(
+    event_set: c_int,
+    address: *mut c_void,
+    overflow_vector: c_longlong,
+    _context: *mut c_void,
+) {
+    let mut indices = [0 as c_int; MAX_OVERFLOW_EVENTS];
+    let mut num_indices = MAX_OVERFLOW_EVENTS as c_int;
+    if ffi::PAPI_get_overflow_event_index(
+        event_set,
+        overflow_vector,
+        indices.as_mut_ptr(),
+        &mut num_indices,
+    ) != ffi::PAPI_OK as c_int
+    {
+        return;
+    }
+
+    let _ = HANDLERS.try_with(|handlers| {
+        let mut handlers = match handlers.try_borrow_mut() {
+            Ok(h) => h,
+            Err(_) => return,
+        };
+        let event_set_handlers = match handlers.get_mut(&event_set) {
+            Some(h) => h,
+            None => return,
+        };
+
+        for &index in indices.iter().take(num_indices.max(0) as usize) {
+            let code = match event_set_handlers.event_codes.get(index as usize) {
+                Some(&code) => code,
+                None => continue,
+            };
+            if let Some(handler) = event_set_handlers.handlers.get_mut(&code) {
+                handler(code, address as usize);
+            }
+        }
+    });
+}
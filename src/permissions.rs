@@ -0,0 +1,187 @@
+// Copyright 2019 German Research Center for Artificial Intelligence (DFKI)
+// Author: Clemens Lutz <clemens.lutz@dfki.de>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Diagnostics of the permissions to measure hardware events.
+//!
+//! On Linux, PAPI measures hardware events with the `perf_event` subsystem.
+//! The `kernel.perf_event_paranoid` sysctl restricts which events
+//! unprivileged users may measure. Restrictive settings are a common cause of
+//! PAPI errors that are otherwise hard to diagnose.
+
+use super::error::{ErrorKind, Result};
+use super::Papi;
+use std::fmt;
+
+/// The permissions to measure hardware events.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PerfPermissions {
+    /// The value of `perf_event_paranoid`, if the system has this setting
+    pub paranoid: Option<i32>,
+    /// The process runs with root privileges, which bypass the restrictions
+    pub privileged: bool,
+    /// Events can be measured in user space
+    pub user: bool,
+    /// Events can be measured in kernel space
+    pub kernel: bool,
+    /// Event sets can be attached to other processes of the same user
+    pub attach: bool,
+    /// System-wide events, such as uncore events, can be measured
+    pub system_wide: bool,
+}
+
+impl Papi {
+    /// Checks which hardware events the current process may measure.
+    ///
+    /// On Linux, reads `/proc/sys/kernel/perf_event_paranoid`. Otherwise,
+    /// reports all measurements as permitted. The `Display` output of the
+    /// result explains how to lift the restrictions.
+    ///
+    ///     # use std::error::Error;
+    ///     # use std::result::Result;
+    ///     # use papi::Papi;
+    ///     #
+    ///     # fn main() -> Result<(), Box<dyn Error>> {
+    ///     # let papi = Papi::init()?;
+    ///     let permissions = papi.check_perf_permissions()?;
+    ///     if !permissions.kernel {
+    ///         println!("{}", permissions);
+    ///     }
+    ///     #
+    ///     # Ok(())
+    ///     # }
+    ///
+    pub fn check_perf_permissions(&self) -> Result<PerfPermissions> {
+        let privileged = unsafe { libc::geteuid() } == 0;
+        let paranoid = procfs::perf_event_paranoid()?;
+
+        Ok(PerfPermissions::new(paranoid, privileged))
+    }
+}
+
+impl PerfPermissions {
+    /// Determines the permissions from the `perf_event_paranoid` level.
+    ///
+    /// The levels are documented in the Linux `perf_event_open(2)` man page.
+    /// Some distributions additionally disallow all measurements at levels
+    /// above 2.
+    fn new(paranoid: Option<i32>, privileged: bool) -> Self {
+        let level = match paranoid {
+            Some(level) if !privileged => level,
+            _ => -1,
+        };
+
+        Self {
+            paranoid,
+            privileged,
+            user: level <= 2,
+            kernel: level <= 1,
+            attach: level <= 2,
+            system_wide: level <= 0,
+        }
+    }
+}
+
+impl fmt::Display for PerfPermissions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let yes_no = |allowed: bool| if allowed { "yes" } else { "no" };
+
+        match self.paranoid {
+            Some(level) => writeln!(f, "perf_event_paranoid: {}", level)?,
+            None => writeln!(f, "perf_event_paranoid: not available")?,
+        }
+        if self.privileged {
+            writeln!(f, "running as root, restrictions do not apply")?;
+        }
+        writeln!(f, "user space events: {}", yes_no(self.user))?;
+        writeln!(f, "kernel space events: {}", yes_no(self.kernel))?;
+        writeln!(f, "attach to processes: {}", yes_no(self.attach))?;
+        writeln!(f, "system-wide events: {}", yes_no(self.system_wide))?;
+
+        if !self.system_wide {
+            write!(
+                f,
+                "hint: run `sysctl -w kernel.perf_event_paranoid={}` as root to lift the restrictions",
+                if self.user { 0 } else { 2 }
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod procfs {
+    use super::{ErrorKind, Result};
+    use std::fs;
+
+    const PARANOID_PATH: &str = "/proc/sys/kernel/perf_event_paranoid";
+
+    /// Reads the `perf_event_paranoid` level.
+    ///
+    /// Returns `None` if the kernel doesn't support `perf_event`.
+    pub(super) fn perf_event_paranoid() -> Result<Option<i32>> {
+        let level = match fs::read_to_string(PARANOID_PATH) {
+            Ok(level) => level,
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => Err(e)?,
+        };
+
+        level.trim().parse().map(Some).map_err(|_| {
+            ErrorKind::InvalidArgument(format!(
+                "Invalid perf_event_paranoid value {}",
+                level.trim()
+            ))
+            .into()
+        })
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod procfs {
+    use super::Result;
+
+    pub(super) fn perf_event_paranoid() -> Result<Option<i32>> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn permissions_of_paranoid_levels() {
+        let unrestricted = PerfPermissions::new(Some(-1), false);
+        assert!(unrestricted.user && unrestricted.kernel && unrestricted.system_wide);
+
+        let kernel = PerfPermissions::new(Some(1), false);
+        assert!(kernel.user && kernel.kernel && kernel.attach);
+        assert!(!kernel.system_wide);
+
+        let user = PerfPermissions::new(Some(2), false);
+        assert!(user.user && user.attach);
+        assert!(!user.kernel && !user.system_wide);
+
+        let disallowed = PerfPermissions::new(Some(3), false);
+        assert!(!disallowed.user && !disallowed.attach);
+    }
+
+    #[test]
+    fn privileged_permissions() {
+        let root = PerfPermissions::new(Some(3), true);
+        assert!(root.user && root.kernel && root.attach && root.system_wide);
+        assert_eq!(root.paranoid, Some(3));
+    }
+
+    #[test]
+    fn read_perf_permissions() {
+        let papi = Papi::init().unwrap();
+        assert!(papi.check_perf_permissions().is_ok());
+    }
+}
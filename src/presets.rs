@@ -0,0 +1,150 @@
+// Copyright 2019 German Research Center for Artificial Intelligence (DFKI)
+// Author: Clemens Lutz <clemens.lutz@dfki.de>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Ready-made event sets for common analyses.
+//!
+//! The relevant native events differ between CPU models. The functions in
+//! this module discover the events available on the current CPU, and build
+//! event sets from them.
+
+use super::error::{ErrorKind, Result};
+use super::event_set::{EventSetBuilder, ReadyEventSet, Sample};
+use super::events::EnumModifier;
+use super::Papi;
+
+/// Native events that count all floating point assists at once.
+///
+/// Intel CPUs up to Broadwell provide `FP_ASSIST:ANY`, whereas later CPUs
+/// provide `ASSISTS:FP`.
+const FP_ASSIST_AGGREGATES: [&str; 2] = ["FP_ASSIST:ANY", "ASSISTS:FP"];
+
+/// The base name of the native events that count individual floating point
+/// assists.
+const FP_ASSIST_PREFIX: &str = "FP_ASSIST:";
+
+/// Builds an event set that counts floating point assists.
+///
+/// Floating point assists occur, e.g., when an operation handles denormal
+/// numbers in microcode, and are thus a common cause of poor floating point
+/// performance.
+///
+/// Prefers events that count all assists at once. Otherwise, adds the events
+/// that count individual kinds of assists. Returns an error if the CPU has no
+/// floating point assist events.
+///
+///     # use std::error::Error;
+///     # use std::result::Result;
+///     # use papi::Papi;
+///     # use papi::event_set::Sample;
+///     use papi::presets;
+///
+///     # fn main() -> Result<(), Box<dyn Error>> {
+///     # let papi = Papi::init()?;
+///     if let Ok(ready_event_set) = presets::fp_assist_event_set(&papi) {
+///         let mut sample = Sample::default();
+///         ready_event_set.init_sample(&mut sample)?;
+///         let running_event_set = ready_event_set.start()?;
+///         running_event_set.stop(&mut sample)?;
+///
+///         println!("FP assists: {}", sample.fp_assists()?);
+///     }
+///     #
+///     # Ok(())
+///     # }
+///
+pub fn fp_assist_event_set(papi: &Papi) -> Result<ReadyEventSet> {
+    let events = papi.native_events(0, EnumModifier::Umasks)?;
+    let selected = select_fp_assist_events(&events);
+
+    if selected.is_empty() {
+        Err(ErrorKind::InvalidArgument(
+            "No floating point assist events available on this CPU".into(),
+        ))?;
+    }
+
+    selected
+        .iter()
+        .try_fold(EventSetBuilder::new(papi)?, |builder, event| {
+            builder.add_event_by_name(event)
+        })?
+        .build()
+}
+
+/// Selects the floating point assist events from a list of native events.
+fn select_fp_assist_events(events: &[String]) -> Vec<&str> {
+    let aggregates: Vec<&str> = events
+        .iter()
+        .map(|e| e.as_str())
+        .filter(|e| FP_ASSIST_AGGREGATES.contains(e))
+        .take(1)
+        .collect();
+
+    if !aggregates.is_empty() {
+        return aggregates;
+    }
+
+    events
+        .iter()
+        .map(|e| e.as_str())
+        .filter(|e| is_fp_assist_event(e))
+        .collect()
+}
+
+/// Tests if the event counts floating point assists.
+fn is_fp_assist_event(name: &str) -> bool {
+    FP_ASSIST_AGGREGATES.contains(&name) || name.starts_with(FP_ASSIST_PREFIX)
+}
+
+impl Sample {
+    /// Sums up the values of all floating point assist events in the sample.
+    ///
+    /// Use with a sample of `fp_assist_event_set`.
+    pub fn fp_assists(&self) -> Result<i64> {
+        Ok(self
+            .named_values()?
+            .iter()
+            .filter(|(name, _)| is_fp_assist_event(name))
+            .map(|(_, value)| value)
+            .sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn prefer_aggregate_fp_assist_events() {
+        let events: Vec<String> = vec![
+            "FP_ASSIST:X87_OUTPUT".into(),
+            "FP_ASSIST:ANY".into(),
+            "UOPS_RETIRED:ALL".into(),
+        ];
+        assert_eq!(select_fp_assist_events(&events), vec!["FP_ASSIST:ANY"]);
+    }
+
+    #[test]
+    fn select_individual_fp_assist_events() {
+        let events: Vec<String> = vec![
+            "FP_ASSIST:X87_OUTPUT".into(),
+            "FP_ASSIST:SIMD_INPUT".into(),
+            "UOPS_RETIRED:ALL".into(),
+        ];
+        assert_eq!(
+            select_fp_assist_events(&events),
+            vec!["FP_ASSIST:X87_OUTPUT", "FP_ASSIST:SIMD_INPUT"]
+        );
+    }
+
+    #[test]
+    fn select_no_fp_assist_events() {
+        let events: Vec<String> = vec!["UOPS_RETIRED:ALL".into()];
+        assert!(select_fp_assist_events(&events).is_empty());
+    }
+}
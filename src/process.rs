@@ -0,0 +1,229 @@
+// Copyright 2019 German Research Center for Artificial Intelligence (DFKI)
+// Author: Clemens Lutz <clemens.lutz@dfki.de>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Measurement of child processes.
+//!
+//! Provides a `ProfiledCommand`, which runs a program in a child process
+//! similar to `std::process::Command`, and samples the hardware events of the
+//! child process.
+//!
+//! # Requirements
+//!
+//! The event set is attached to the child process by PAPI. This requires the
+//! same permissions as tracing the child process with `ptrace`, i.e., the
+//! child must run as the same user, and the `kernel.yama.ptrace_scope` and
+//! `kernel.perf_event_paranoid` sysctls must permit tracing processes.
+
+use super::error::{ErrorKind, Result};
+use super::event_set::{EventSetBuilder, RunningEventSet, Sample};
+use super::Papi;
+use std::ffi::{CString, OsStr, OsString};
+use std::io;
+use std::iter;
+use std::os::raw::c_char;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::ptr;
+
+/// A builder for a child process that is measured from its first instruction
+/// until it exits.
+///
+/// The child process is stopped before it executes the program. While the
+/// child is stopped, the event set is attached to it with inheritance
+/// enabled. Thus, the sample includes threads and processes spawned by the
+/// program.
+///
+///     # use std::error::Error;
+///     # use std::result::Result;
+///     # use papi::Papi;
+///     use papi::process::ProfiledCommand;
+///
+///     # fn main() -> Result<(), Box<dyn Error>> {
+///     # let papi = Papi::init()?;
+///     let (status, sample) = ProfiledCommand::new(&papi, "ls")
+///         .arg("-l")
+///         .add_event_by_name("PAPI_TOT_INS")
+///         .status()?;
+///
+///     println!("Exited with {}: {}", status, sample);
+///     #
+///     # Ok(())
+///     # }
+///
+#[derive(Debug)]
+pub struct ProfiledCommand<'p> {
+    papi: &'p Papi,
+    program: OsString,
+    args: Vec<OsString>,
+    events: Vec<String>,
+}
+
+impl<'p> ProfiledCommand<'p> {
+    /// Creates a new `ProfiledCommand` for launching the program.
+    ///
+    /// The program is searched in the `PATH`.
+    pub fn new<S: AsRef<OsStr>>(papi: &'p Papi, program: S) -> Self {
+        Self {
+            papi,
+            program: program.as_ref().to_owned(),
+            args: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Adds an argument to pass to the program.
+    pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    /// Adds multiple arguments to pass to the program.
+    pub fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args
+            .extend(args.into_iter().map(|a| a.as_ref().to_owned()));
+        self
+    }
+
+    /// Adds a hardware event specified by its name to measure.
+    pub fn add_event_by_name(&mut self, name: &str) -> &mut Self {
+        self.events.push(name.to_owned());
+        self
+    }
+
+    /// Executes the program as a child process, waits for it to exit, and
+    /// returns its exit status together with the sample.
+    pub fn status(&mut self) -> Result<(ExitStatus, Sample)> {
+        // Allocate before forking, as the child must not allocate memory
+        let argv = iter::once(&self.program)
+            .chain(self.args.iter())
+            .map(|arg| {
+                CString::new(arg.as_bytes()).map_err(|_| {
+                    ErrorKind::InvalidArgument(format!("Invalid argument {:?}", arg)).into()
+                })
+            })
+            .collect::<Result<Vec<CString>>>()?;
+        let argv_ptrs: Vec<*const c_char> = argv
+            .iter()
+            .map(|arg| arg.as_ptr())
+            .chain(iter::once(ptr::null()))
+            .collect();
+
+        let pid = unsafe { libc::fork() };
+        if pid < 0 {
+            Err(io::Error::last_os_error())?;
+        } else if pid == 0 {
+            // Child: wait for the parent to attach, then run the program
+            unsafe {
+                libc::raise(libc::SIGSTOP);
+                libc::execvp(argv_ptrs[0], argv_ptrs.as_ptr());
+                libc::_exit(127);
+            }
+        }
+
+        wait_stopped(pid).inspect_err(|_| kill_child(pid))?;
+
+        let (running, mut sample) = self.start_attached(pid).inspect_err(|_| kill_child(pid))?;
+
+        unsafe {
+            if libc::kill(pid, libc::SIGCONT) != 0 {
+                let e = io::Error::last_os_error();
+                kill_child(pid);
+                Err(e)?;
+            }
+        }
+
+        let status = wait_exited(pid)?;
+        running.stop(&mut sample)?;
+
+        Ok((ExitStatus::from_raw(status), sample))
+    }
+
+    /// Builds and starts an event set attached to the child process.
+    fn start_attached(&self, pid: libc::pid_t) -> Result<(RunningEventSet, Sample)> {
+        let ready_event_set = self
+            .events
+            .iter()
+            .try_fold(
+                EventSetBuilder::new(self.papi)?
+                    .set_inherit(true)?
+                    .attach(pid)?,
+                |builder, event| builder.add_event_by_name(event),
+            )?
+            .build()?;
+
+        let mut sample = Sample::default();
+        ready_event_set.init_sample(&mut sample)?;
+        let running = ready_event_set.start()?;
+
+        Ok((running, sample))
+    }
+}
+
+/// Waits until the child process has stopped.
+fn wait_stopped(pid: libc::pid_t) -> Result<()> {
+    let status = wait_pid(pid, libc::WUNTRACED)?;
+    if !libc::WIFSTOPPED(status) {
+        Err(ErrorKind::InvalidArgument(
+            "Child process exited before it could be measured".into(),
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Waits until the child process has exited, and returns its wait status.
+fn wait_exited(pid: libc::pid_t) -> Result<i32> {
+    wait_pid(pid, 0)
+}
+
+/// Waits for a state change of the child process, retrying on interrupts.
+fn wait_pid(pid: libc::pid_t, options: i32) -> Result<i32> {
+    let mut status = 0;
+    loop {
+        if unsafe { libc::waitpid(pid, &mut status, options) } >= 0 {
+            return Ok(status);
+        }
+
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            Err(e)?;
+        }
+    }
+}
+
+/// Kills and reaps the child process after a failure.
+fn kill_child(pid: libc::pid_t) {
+    unsafe {
+        libc::kill(pid, libc::SIGKILL);
+    }
+    let _ = wait_pid(pid, 0);
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    #[ignore]
+    fn measure_child_process() {
+        let papi = Papi::init().unwrap();
+        let (status, sample) = ProfiledCommand::new(&papi, "true")
+            .add_event_by_name("PAPI_TOT_INS")
+            .status()
+            .unwrap();
+
+        assert!(status.success());
+        assert!(sample.into_iter().all(|(_, value)| value > 0));
+    }
+}
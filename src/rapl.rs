@@ -0,0 +1,107 @@
+// Copyright 2019 German Research Center for Artificial Intelligence (DFKI)
+// Author: Clemens Lutz <clemens.lutz@dfki.de>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Discovery of RAPL energy counters.
+//!
+//! RAPL (Running Average Power Limit) provides energy counters for each
+//! package (i.e., socket) of the system. PAPI exposes these counters as
+//! native events of the `rapl` component, with one event per package, e.g.,
+//! `rapl:::PACKAGE_ENERGY:PACKAGE0`.
+
+use super::error::Result;
+use super::events::{component_index, EnumModifier};
+use super::ffi;
+use super::Papi;
+use std::collections::BTreeMap;
+
+/// The RAPL component name.
+const RAPL_COMPONENT: &str = "rapl";
+
+/// The suffix that identifies the package of a RAPL event.
+const PACKAGE_SUFFIX: &str = ":PACKAGE";
+
+/// The RAPL energy events of a package.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PackageInfo {
+    /// The package number
+    pub package: u32,
+    /// The names of the events measuring the package
+    pub events: Vec<String>,
+}
+
+/// Discovers the packages of the system and their RAPL events.
+///
+/// The packages are sorted by their package number. Returns an empty list if
+/// the RAPL component is not available, or is disabled, e.g., because RAPL
+/// requires root privileges.
+///
+///     # use std::error::Error;
+///     # use std::result::Result;
+///     # use papi::Papi;
+///     use papi::rapl;
+///
+///     # fn main() -> Result<(), Box<dyn Error>> {
+///     # let papi = Papi::init()?;
+///     for package in rapl::packages(&papi)? {
+///         println!("Package {}: {:?}", package.package, package.events);
+///     }
+///     #
+///     # Ok(())
+///     # }
+///
+pub fn packages(papi: &Papi) -> Result<Vec<PackageInfo>> {
+    let component = match component_index(RAPL_COMPONENT)? {
+        Some(c) => c,
+        None => return Ok(Vec::new()),
+    };
+
+    let info = unsafe { ffi::PAPI_get_component_info(component) };
+    if info.is_null() || unsafe { (*info).disabled } != 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut packages: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+    for event in papi.native_events(component, EnumModifier::All)? {
+        if let Some(package) = package_number(&event) {
+            packages.entry(package).or_default().push(event);
+        }
+    }
+
+    Ok(packages
+        .into_iter()
+        .map(|(package, events)| PackageInfo { package, events })
+        .collect())
+}
+
+/// Parses the package number from a RAPL event name.
+fn package_number(event: &str) -> Option<u32> {
+    let index = event.rfind(PACKAGE_SUFFIX)?;
+    event[index + PACKAGE_SUFFIX.len()..].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn parse_package_number() {
+        assert_eq!(package_number("rapl:::PACKAGE_ENERGY:PACKAGE0"), Some(0));
+        assert_eq!(package_number("rapl:::DRAM_ENERGY:PACKAGE12"), Some(12));
+        assert_eq!(package_number("rapl:::THERMAL_SPEC"), None);
+    }
+
+    #[test]
+    #[ignore]
+    fn discover_packages() {
+        let papi = Papi::init().unwrap();
+        let packages = packages(&papi).unwrap();
+        assert!(!packages.is_empty());
+        assert!(packages.iter().all(|p| !p.events.is_empty()));
+    }
+}
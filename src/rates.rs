@@ -0,0 +1,225 @@
+// Copyright 2019 German Research Center for Artificial Intelligence (DFKI)
+// Author: Clemens Lutz <clemens.lutz@dfki.de>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Wrappers around the PAPI rate functions.
+//!
+//! The rate functions measure common metrics without setting up an event set.
+//! The first call starts the counters, and each subsequent call returns the
+//! values since the first call.
+//!
+//! PAPI additionally reports its own real and process times. As PAPI's clock
+//! cannot be correlated with other measurements, each reading also captures
+//! the real and virtual time in nanoseconds at the time of the call.
+//!
+//! Note that the rate functions internally start an event set on the calling
+//! thread. Thus, they conflict with event sets that are running on the same
+//! thread. Call `reset` to release the internal event set.
+
+use super::error::{check, Result};
+use super::events::event_name_to_code;
+use super::ffi;
+
+/// A reading of the instructions per cycle rate.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IpcRate {
+    /// Real time in seconds since the first call
+    pub real_time: f32,
+    /// Process time in seconds since the first call
+    pub proc_time: f32,
+    /// Instructions retired since the first call
+    pub instructions: i64,
+    /// Instructions per cycle since the first call
+    pub ipc: f32,
+    /// Real time in nanoseconds at the time of the reading
+    pub real_nsec: i64,
+    /// Virtual time in nanoseconds at the time of the reading
+    pub virt_nsec: i64,
+}
+
+/// A reading of the floating point operations rate.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FlopsRate {
+    /// Real time in seconds since the first call
+    pub real_time: f32,
+    /// Process time in seconds since the first call
+    pub proc_time: f32,
+    /// Floating point operations since the first call
+    pub flpops: i64,
+    /// Millions of floating point operations per second since the first call
+    pub mflops: f32,
+    /// Real time in nanoseconds at the time of the reading
+    pub real_nsec: i64,
+    /// Virtual time in nanoseconds at the time of the reading
+    pub virt_nsec: i64,
+}
+
+/// A reading of the events per cycle rate.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EpcRate {
+    /// Real time in seconds since the first call
+    pub real_time: f32,
+    /// Process time in seconds since the first call
+    pub proc_time: f32,
+    /// Reference cycles since the first call
+    pub reference_cycles: i64,
+    /// Core cycles since the first call
+    pub core_cycles: i64,
+    /// Events since the first call
+    pub events: i64,
+    /// Events per cycle since the first call
+    pub epc: f32,
+    /// Real time in nanoseconds at the time of the reading
+    pub real_nsec: i64,
+    /// Virtual time in nanoseconds at the time of the reading
+    pub virt_nsec: i64,
+}
+
+/// Reads the instructions per cycle rate.
+///
+///     # use std::error::Error;
+///     # use std::result::Result;
+///     #
+///     # fn main() -> Result<(), Box<dyn Error>> {
+///     # let _papi = papi::Papi::init()?;
+///     let rate = papi::rates::ipc()?;
+///     println!("IPC: {}", rate.ipc);
+///     #
+///     # Ok(())
+///     # }
+///
+pub fn ipc() -> Result<IpcRate> {
+    let mut rate = IpcRate::default();
+
+    unsafe {
+        check(ffi::PAPI_ipc(
+            &mut rate.real_time,
+            &mut rate.proc_time,
+            &mut rate.instructions,
+            &mut rate.ipc,
+        ))?;
+        rate.real_nsec = ffi::PAPI_get_real_nsec();
+        rate.virt_nsec = ffi::PAPI_get_virt_nsec();
+    }
+
+    Ok(rate)
+}
+
+/// Reads the floating point operations rate.
+///
+///     # use std::error::Error;
+///     # use std::result::Result;
+///     #
+///     # fn main() -> Result<(), Box<dyn Error>> {
+///     # let _papi = papi::Papi::init()?;
+///     let rate = papi::rates::flops()?;
+///     println!("MFLOPS: {}", rate.mflops);
+///     #
+///     # Ok(())
+///     # }
+///
+pub fn flops() -> Result<FlopsRate> {
+    let mut rate = FlopsRate::default();
+    let event = event_name_to_code("PAPI_FP_OPS")?;
+
+    unsafe {
+        check(ffi::PAPI_flops_rate(
+            event,
+            &mut rate.real_time,
+            &mut rate.proc_time,
+            &mut rate.flpops,
+            &mut rate.mflops,
+        ))?;
+        rate.real_nsec = ffi::PAPI_get_real_nsec();
+        rate.virt_nsec = ffi::PAPI_get_virt_nsec();
+    }
+
+    Ok(rate)
+}
+
+/// Reads the events per cycle rate of an event.
+///
+///     # use std::error::Error;
+///     # use std::result::Result;
+///     #
+///     # fn main() -> Result<(), Box<dyn Error>> {
+///     # let _papi = papi::Papi::init()?;
+///     let rate = papi::rates::epc("PAPI_TOT_INS")?;
+///     println!("EPC: {}", rate.epc);
+///     #
+///     # Ok(())
+///     # }
+///
+pub fn epc(event_name: &str) -> Result<EpcRate> {
+    let mut rate = EpcRate::default();
+    let event = event_name_to_code(event_name)?;
+
+    unsafe {
+        check(ffi::PAPI_epc(
+            event,
+            &mut rate.real_time,
+            &mut rate.proc_time,
+            &mut rate.reference_cycles,
+            &mut rate.core_cycles,
+            &mut rate.events,
+            &mut rate.epc,
+        ))?;
+        rate.real_nsec = ffi::PAPI_get_real_nsec();
+        rate.virt_nsec = ffi::PAPI_get_virt_nsec();
+    }
+
+    Ok(rate)
+}
+
+/// Resets the state of the rate functions.
+///
+/// Stops the counters of the rate functions, and destroys the internal event
+/// set together with the start times and counts of the first call. Thus, the
+/// next call to a rate function starts a fresh measurement series. This also
+/// allows switching between the rate functions, as only one of them can be
+/// active at a time.
+///
+///     # use std::error::Error;
+///     # use std::result::Result;
+///     #
+///     # fn main() -> Result<(), Box<dyn Error>> {
+///     # let _papi = papi::Papi::init()?;
+///     let first_series = papi::rates::ipc()?;
+///     papi::rates::reset()?;
+///     let second_series = papi::rates::ipc()?;
+///     #
+///     # Ok(())
+///     # }
+///
+pub fn reset() -> Result<()> {
+    unsafe {
+        check(ffi::PAPI_rate_stop())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::Papi;
+
+    #[test]
+    #[ignore]
+    fn read_flops_rate() {
+        let _papi = Papi::init().unwrap();
+        flops().unwrap();
+
+        let _sum: f64 = std::hint::black_box((0..1_000_000).map(|i| i as f64 * 0.5)).sum();
+        let rate = flops().unwrap();
+        reset().unwrap();
+
+        assert!(rate.flpops > 0);
+        assert!(rate.real_time > 0.0);
+    }
+}
@@ -0,0 +1,321 @@
+// Copyright 2019 German Research Center for Artificial Intelligence (DFKI)
+// Author: Clemens Lutz <clemens.lutz@dfki.de>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Export of measurement results.
+//!
+//! Collects samples and writes them in formats suitable for further
+//! processing by other tools.
+
+use super::error::{ErrorKind, Result};
+use super::event_set::Sample;
+#[cfg(feature = "json")]
+use serde_derive::Serialize;
+use std::io::Write;
+
+/// A series of samples, each with an optional capture timestamp.
+///
+///     # use std::error::Error;
+///     # use std::result::Result;
+///     # use papi::Papi;
+///     # use papi::event_set::{EventSetBuilder, Sample};
+///     use papi::report::SampleSeries;
+///
+///     # fn main() -> Result<(), Box<dyn Error>> {
+///     # let papi = Papi::init()?;
+///     # let ready_event_set = EventSetBuilder::new(&papi)?
+///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+///     #     .build()?;
+///     # let mut sample = Sample::default();
+///     # ready_event_set.init_sample(&mut sample)?;
+///     # let running_event_set = ready_event_set.start()?;
+///     # running_event_set.stop(&mut sample)?;
+///     #
+///     let mut series = SampleSeries::new();
+///     series.push(sample);
+///
+///     # #[cfg(feature = "json")]
+///     # {
+///     let stdout = std::io::stdout();
+///     series.write_jsonl(stdout.lock())?;
+///     # }
+///     #
+///     # Ok(())
+///     # }
+///
+#[derive(Clone, Debug, Default)]
+pub struct SampleSeries {
+    entries: Vec<(Option<i64>, Sample)>,
+}
+
+/// A line of the JSON Lines format.
+#[cfg(feature = "json")]
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<i64>,
+    values: &'a Sample,
+}
+
+impl SampleSeries {
+    /// Creates a new, empty series.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a sample without a timestamp.
+    pub fn push(&mut self, sample: Sample) {
+        self.entries.push((None, sample));
+    }
+
+    /// Appends a sample captured at the given timestamp in nanoseconds.
+    pub fn push_timed(&mut self, timestamp_nsec: i64, sample: Sample) {
+        self.entries.push((Some(timestamp_nsec), sample));
+    }
+
+    /// Returns the number of samples in the series.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the series contains no samples.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Writes the series in the JSON Lines format.
+    ///
+    /// Each sample is written as one JSON object per line. The object maps
+    /// `values` to an object of event names and their values, and contains the
+    /// `timestamp` if the sample was pushed with one, e.g.:
+    ///
+    /// ```text
+    /// {"timestamp":1573729805000000000,"values":{"PAPI_TOT_INS":1234}}
+    /// ```
+    ///
+    /// As each line is self-contained, the output can be appended to an
+    /// existing file.
+    ///
+    /// Requires the `json` feature.
+    #[cfg(feature = "json")]
+    pub fn write_jsonl<W: Write>(&self, mut writer: W) -> Result<()> {
+        for (timestamp, sample) in &self.entries {
+            let line = JsonLine {
+                timestamp: *timestamp,
+                values: sample,
+            };
+            serde_json::to_writer(&mut writer, &line)?;
+            writeln!(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes samples in the CSV format.
+///
+/// Each row consists of the values of the parameter columns, followed by the
+/// values of the sample. The header consists of the parameter column names,
+/// followed by the event names sorted in lexicographical order, e.g.:
+///
+/// ```text
+/// size,threads,PAPI_TOT_CYC,PAPI_TOT_INS
+/// 1024,4,5678,1234
+/// ```
+///
+/// All samples must be measured by the same event set, and each row must
+/// contain one value per parameter column. Fields containing commas, quotes,
+/// or line breaks are quoted.
+///
+///     # use std::error::Error;
+///     # use std::result::Result;
+///     # use papi::Papi;
+///     # use papi::event_set::{EventSetBuilder, Sample};
+///     use papi::report::write_csv;
+///
+///     # fn main() -> Result<(), Box<dyn Error>> {
+///     # let papi = Papi::init()?;
+///     # let ready_event_set = EventSetBuilder::new(&papi)?
+///     #     .add_event_by_name("CPU_CLK_UNHALTED")?
+///     #     .build()?;
+///     # let mut sample = Sample::default();
+///     # ready_event_set.init_sample(&mut sample)?;
+///     # let running_event_set = ready_event_set.start()?;
+///     # running_event_set.stop(&mut sample)?;
+///     #
+///     let rows = vec![(vec!["1024".to_string()], &sample)];
+///
+///     let stdout = std::io::stdout();
+///     write_csv(stdout.lock(), &["size"], &rows)?;
+///     #
+///     # Ok(())
+///     # }
+///
+pub fn write_csv<W: Write>(
+    mut writer: W,
+    header_cols: &[&str],
+    rows: &[(Vec<String>, &Sample)],
+) -> Result<()> {
+    if let Some((_, first)) = rows.first() {
+        if rows
+            .iter()
+            .any(|(_, sample)| sample.event_set_hash() != first.event_set_hash())
+        {
+            Err(ErrorKind::InvalidArgument(
+                "Samples are from different event sets".into(),
+            ))?;
+        }
+    }
+
+    if rows
+        .iter()
+        .any(|(params, _)| params.len() != header_cols.len())
+    {
+        Err(ErrorKind::InvalidArgument(
+            "Number of parameters doesn't match the header".into(),
+        ))?;
+    }
+
+    let sorted_values = |sample: &Sample| -> Result<Vec<(String, i64)>> {
+        let mut values = sample.named_values()?;
+        values.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(values)
+    };
+
+    let event_names = match rows.first() {
+        Some((_, first)) => sorted_values(first)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let header = header_cols
+        .iter()
+        .copied()
+        .chain(event_names.iter().map(|name| name.as_str()));
+    for (i, col) in header.enumerate() {
+        if i != 0 {
+            write!(writer, ",")?;
+        }
+        write_csv_field(&mut writer, col)?;
+    }
+    writeln!(writer)?;
+
+    for (params, sample) in rows {
+        for (i, param) in params.iter().enumerate() {
+            if i != 0 {
+                write!(writer, ",")?;
+            }
+            write_csv_field(&mut writer, param)?;
+        }
+        for (i, (_, value)) in sorted_values(sample)?.iter().enumerate() {
+            if i != 0 || !params.is_empty() {
+                write!(writer, ",")?;
+            }
+            write!(writer, "{}", value)?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a string as a CSV field, quoting it if necessary.
+fn write_csv_field<W: Write>(writer: &mut W, s: &str) -> Result<()> {
+    if s.contains([',', '"', '\n', '\r']) {
+        write!(writer, "\"{}\"", s.replace('"', "\"\""))?;
+    } else {
+        write!(writer, "{}", s)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::event_set::EventSetBuilder;
+    use crate::Papi;
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn write_json_lines() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut sample = Sample::default();
+        ready_event_set.init_sample(&mut sample).unwrap();
+        let running = ready_event_set.start().unwrap();
+        running.stop(&mut sample).unwrap();
+
+        let mut series = SampleSeries::new();
+        series.push(sample.clone());
+        series.push_timed(42, sample);
+
+        let mut buffer = Vec::new();
+        series.write_jsonl(&mut buffer).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("{\"values\":{\"PAPI_TOT_INS\":"));
+        assert!(lines[1].starts_with("{\"timestamp\":42,\"values\":{\"PAPI_TOT_INS\":"));
+    }
+
+    #[test]
+    fn quote_csv_field() {
+        let mut buffer = Vec::new();
+        write_csv_field(&mut buffer, "a,\"b\"").unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), "\"a,\"\"b\"\"\"");
+    }
+
+    #[test]
+    fn write_csv_rows() {
+        let papi = Papi::init().unwrap();
+        let ready_event_set = EventSetBuilder::new(&papi)
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_INS")
+            .unwrap()
+            .add_event_by_name("PAPI_TOT_CYC")
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut sample = Sample::default();
+        ready_event_set.init_sample(&mut sample).unwrap();
+        let running = ready_event_set.start().unwrap();
+        running.stop(&mut sample).unwrap();
+
+        let rows = vec![
+            (vec!["1024".to_string(), "1".to_string()], &sample),
+            (vec!["2048".to_string(), "2".to_string()], &sample),
+        ];
+
+        let mut buffer = Vec::new();
+        write_csv(&mut buffer, &["size", "threads"], &rows).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        let expected = |params: &str| {
+            format!(
+                "{},{},{}",
+                params,
+                sample.value_by_name("PAPI_TOT_CYC").unwrap(),
+                sample.value_by_name("PAPI_TOT_INS").unwrap()
+            )
+        };
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "size,threads,PAPI_TOT_CYC,PAPI_TOT_INS");
+        assert_eq!(lines[1], expected("1024,1"));
+        assert_eq!(lines[2], expected("2048,2"));
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright 2019 German Research Center for Artificial Intelligence (DFKI)
+// Author: Clemens Lutz <clemens.lutz@dfki.de>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Wrappers around the PAPI timer functions.
+//!
+//! The timers use the same clock sources as PAPI's own measurements. Thus,
+//! they avoid mixing clocks when correlating times with counter values. The
+//! real timers measure wall clock time, whereas the virtual timers measure
+//! the time that the process spent running on a CPU.
+//!
+//! The timers require an initialized library, but no event set.
+//!
+//!     # use std::error::Error;
+//!     # use std::result::Result;
+//!     #
+//!     # fn main() -> Result<(), Box<dyn Error>> {
+//!     let _papi = papi::Papi::init()?;
+//!
+//!     let start = papi::timing::real_nsec();
+//!     let _sum: u32 = (0..100).sum();
+//!     println!("Took {} ns", papi::timing::real_nsec() - start);
+//!     #
+//!     # Ok(())
+//!     # }
+//!
+
+use super::ffi;
+
+/// Returns the wall clock time in nanoseconds.
+pub fn real_nsec() -> i64 {
+    unsafe { ffi::PAPI_get_real_nsec() }
+}
+
+/// Returns the wall clock time in clock cycles.
+pub fn real_cyc() -> i64 {
+    unsafe { ffi::PAPI_get_real_cyc() }
+}
+
+/// Returns the virtual time of the process in nanoseconds.
+pub fn virt_nsec() -> i64 {
+    unsafe { ffi::PAPI_get_virt_nsec() }
+}
+
+/// Returns the virtual time of the process in clock cycles.
+pub fn virt_cyc() -> i64 {
+    unsafe { ffi::PAPI_get_virt_cyc() }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::Papi;
+
+    #[test]
+    fn real_time_is_monotonic() {
+        let _papi = Papi::init().unwrap();
+        let first = real_nsec();
+        let second = real_nsec();
+
+        assert!(first > 0);
+        assert!(second >= first);
+    }
+}
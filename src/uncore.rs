@@ -0,0 +1,178 @@
+// Copyright 2019 German Research Center for Artificial Intelligence (DFKI)
+// Author: Clemens Lutz <clemens.lutz@dfki.de>
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Measurement of uncore events.
+//!
+//! The uncore of Intel server CPUs splits the last-level cache (LLC) into
+//! slices, each of which is managed by its own caching agent. Depending on
+//! the CPU generation, these are called CHA (Skylake-SP and later) or CBo
+//! (Haswell-EP and Broadwell-EP). PAPI exposes each caching agent as a
+//! separate PMU of the `perf_event_uncore` component, e.g.,
+//! `skx_unc_cha0::UNC_CHA_LLC_LOOKUP:ANY`.
+
+use super::error::{ErrorKind, Result};
+use super::event_set::{EventSetBuilder, ReadyEventSet, Sample};
+use super::events::{component_index, EnumModifier};
+use super::Papi;
+
+/// The uncore component name.
+const UNCORE_COMPONENT: &str = "perf_event_uncore";
+
+/// The separator between the PMU name and the event name.
+const PMU_SEPARATOR: &str = "::";
+
+/// The per-slice events that count LLC lookups and victimized lines.
+///
+/// Lookups count the lines read from the LLC, and victims of modified lines
+/// count the lines written back to memory.
+const LLC_EVENTS: [&str; 4] = [
+    "UNC_CHA_LLC_LOOKUP:ANY",
+    "UNC_CHA_LLC_VICTIMS:TOTAL_M",
+    "UNC_C_LLC_LOOKUP:ANY",
+    "UNC_C_LLC_VICTIMS:M_STATE",
+];
+
+/// The size of a cache line in bytes on CPUs with CHA or CBo uncore units.
+const CACHE_LINE_SIZE: i64 = 64;
+
+/// Builds an event set that measures the LLC bandwidth.
+///
+/// Discovers the LLC lookup and victim events of all LLC slices, and adds
+/// them to the event set. Returns an error if the uncore component is not
+/// available, or if the CPU has no CHA or CBo LLC events.
+///
+/// Note that uncore events usually require elevated privileges, e.g., a
+/// `perf_event_paranoid` setting of 0 or lower.
+///
+///     # use std::error::Error;
+///     # use std::result::Result;
+///     # use papi::Papi;
+///     # use papi::event_set::Sample;
+///     use papi::uncore;
+///
+///     # fn main() -> Result<(), Box<dyn Error>> {
+///     # let papi = Papi::init()?;
+///     if let Ok(ready_event_set) = uncore::llc_bandwidth_set(&papi) {
+///         let mut sample = Sample::default();
+///         ready_event_set.init_sample(&mut sample)?;
+///         let running_event_set = ready_event_set.start()?;
+///         running_event_set.stop(&mut sample)?;
+///
+///         println!("LLC bytes: {}", sample.llc_bandwidth_bytes()?);
+///     }
+///     #
+///     # Ok(())
+///     # }
+///
+pub fn llc_bandwidth_set(papi: &Papi) -> Result<ReadyEventSet> {
+    let component = match component_index(UNCORE_COMPONENT)? {
+        Some(c) => c,
+        None => Err(ErrorKind::InvalidArgument(
+            "The uncore component is not available".into(),
+        ))?,
+    };
+
+    let events = papi.native_events(component, EnumModifier::Umasks)?;
+    let selected = select_llc_events(&events);
+
+    if selected.is_empty() {
+        Err(ErrorKind::InvalidArgument(
+            "No CHA or CBo LLC events available on this CPU".into(),
+        ))?;
+    }
+
+    selected
+        .iter()
+        .try_fold(EventSetBuilder::new(papi)?, |builder, event| {
+            builder.add_event_by_name(event)
+        })?
+        .build()
+}
+
+/// Selects the LLC events of all slices from a list of native events.
+fn select_llc_events(events: &[String]) -> Vec<&str> {
+    events
+        .iter()
+        .map(|e| e.as_str())
+        .filter(|e| is_llc_event(e))
+        .collect()
+}
+
+/// Tests if the event counts LLC lookups or victims of a slice.
+fn is_llc_event(name: &str) -> bool {
+    let event = match name.find(PMU_SEPARATOR) {
+        Some(index) => &name[index + PMU_SEPARATOR.len()..],
+        None => name,
+    };
+
+    LLC_EVENTS.contains(&event)
+}
+
+impl Sample {
+    /// Computes the LLC bandwidth in bytes.
+    ///
+    /// Sums up the values of all LLC events in the sample, and scales the sum
+    /// by the cache line size. Use with a sample of `llc_bandwidth_set`.
+    pub fn llc_bandwidth_bytes(&self) -> Result<i64> {
+        let lines: i64 = self
+            .named_values()?
+            .iter()
+            .filter(|(name, _)| is_llc_event(name))
+            .map(|(_, value)| value)
+            .sum();
+
+        Ok(lines * CACHE_LINE_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn select_cha_llc_events() {
+        let events: Vec<String> = vec![
+            "skx_unc_cha0::UNC_CHA_LLC_LOOKUP:ANY".into(),
+            "skx_unc_cha0::UNC_CHA_LLC_LOOKUP:DATA_READ".into(),
+            "skx_unc_cha0::UNC_CHA_LLC_VICTIMS:TOTAL_M".into(),
+            "skx_unc_cha1::UNC_CHA_LLC_LOOKUP:ANY".into(),
+            "skx_unc_imc0::UNC_M_CAS_COUNT:RD".into(),
+        ];
+        assert_eq!(
+            select_llc_events(&events),
+            vec![
+                "skx_unc_cha0::UNC_CHA_LLC_LOOKUP:ANY",
+                "skx_unc_cha0::UNC_CHA_LLC_VICTIMS:TOTAL_M",
+                "skx_unc_cha1::UNC_CHA_LLC_LOOKUP:ANY",
+            ]
+        );
+    }
+
+    #[test]
+    fn select_cbo_llc_events() {
+        let events: Vec<String> = vec![
+            "hswep_unc_cbo0::UNC_C_LLC_LOOKUP:ANY".into(),
+            "hswep_unc_cbo0::UNC_C_LLC_VICTIMS:M_STATE".into(),
+            "hswep_unc_cbo0::UNC_C_LLC_VICTIMS:E_STATE".into(),
+        ];
+        assert_eq!(
+            select_llc_events(&events),
+            vec![
+                "hswep_unc_cbo0::UNC_C_LLC_LOOKUP:ANY",
+                "hswep_unc_cbo0::UNC_C_LLC_VICTIMS:M_STATE",
+            ]
+        );
+    }
+
+    #[test]
+    fn select_no_llc_events() {
+        let events: Vec<String> = vec!["skx_unc_imc0::UNC_M_CAS_COUNT:RD".into()];
+        assert!(select_llc_events(&events).is_empty());
+    }
+}